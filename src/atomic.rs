@@ -0,0 +1,343 @@
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::{bit_read, bit_write};
+
+/// Atomic word of a shared bit buffer. Bits are numbered from the most
+/// significant bit of the first word, as if the words were stored in
+/// big-endian, so for [`AtomicU8`] the layout is the same as for bytes
+pub trait AtomicWord {
+    /// Size of the word in bits
+    const BITS: usize;
+
+    fn load(&self, order: Ordering) -> u64;
+
+    fn swap(&self, value: u64, order: Ordering) -> u64;
+
+    fn fetch_and(&self, value: u64, order: Ordering) -> u64;
+
+    fn compare_exchange_weak(
+        &self,
+        current: u64,
+        new: u64,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u64, u64>;
+}
+
+macro_rules! impl_atomic_word {
+    ($($atomic:ty => $ty:ty),*) => {
+        $(
+            impl AtomicWord for $atomic {
+                const BITS: usize = <$ty>::BITS as usize;
+
+                fn load(&self, order: Ordering) -> u64 {
+                    <$atomic>::load(self, order) as u64
+                }
+
+                fn swap(&self, value: u64, order: Ordering) -> u64 {
+                    <$atomic>::swap(self, value as $ty, order) as u64
+                }
+
+                fn fetch_and(&self, value: u64, order: Ordering) -> u64 {
+                    <$atomic>::fetch_and(self, value as $ty, order) as u64
+                }
+
+                fn compare_exchange_weak(
+                    &self,
+                    current: u64,
+                    new: u64,
+                    success: Ordering,
+                    failure: Ordering,
+                ) -> Result<u64, u64> {
+                    <$atomic>::compare_exchange_weak(
+                        self,
+                        current as $ty,
+                        new as $ty,
+                        success,
+                        failure,
+                    )
+                    .map(|value| value as u64)
+                    .map_err(|value| value as u64)
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_word!(AtomicU8 => u8, AtomicU64 => u64);
+
+/// Same as [`bit_write`], but for a shared buffer of atomic words
+///
+/// Unlike [`bit_write`], the written bits are replaced instead of
+/// ORed, since a separate cleaning would not be atomic. Words that are
+/// partially affected are updated with a compare-and-swap loop, so
+/// concurrent writers to the neighbouring bits never clobber each other.
+/// `order` is used for every update of a word
+///
+/// **PANIC**: If requested bit_size large than source bit size
+///
+/// **NOTE**: For the source, it does not check if the value exceeds the possible range,
+/// that is, the most significant bits, that out of `bit_size`, are simply discarded.
+pub fn atomic_bit_write<W, S>(
+    target: &[W],
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    byte_source_len: usize,
+    order: Ordering,
+) where
+    W: AtomicWord,
+    S: Index<usize, Output = u8>,
+{
+    assert!(
+        recordable_bit_size <= byte_source_len * 8,
+        "recordable_bit_size large than source bit size"
+    );
+
+    // Offset of the written bits in the source (they are the least significant)
+    let source_bit_offset = byte_source_len * 8 - recordable_bit_size;
+
+    for_each_word::<W>(
+        target_bit_offset,
+        recordable_bit_size,
+        |index, from, to, done| {
+            // Read the bits for the word, then place them at the word position
+            let mut chunk = [0u8; 8];
+            let chunk_len = chunk.len();
+            bit_read(
+                source,
+                source_bit_offset + done,
+                to - from,
+                &mut chunk,
+                chunk_len,
+            );
+            let value = u64::from_be_bytes(chunk) << (W::BITS - to);
+
+            let mask = word_mask::<W>(from, to);
+            if mask == word_mask::<W>(0, W::BITS) {
+                target[index].swap(value, order);
+            } else {
+                update(&target[index], mask, value, order);
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests_atomic_bit_write {
+    use super::*;
+
+    fn bytes(target: &[AtomicU8]) -> Vec<u8> {
+        target
+            .iter()
+            .map(|byte| byte.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    #[test]
+    fn check_bytes() {
+        let target: Vec<AtomicU8> = [0b11111111u8, 0b00000000, 0b11111111]
+            .into_iter()
+            .map(AtomicU8::new)
+            .collect();
+        let source = [0b00001010u8, 0b00000101u8];
+
+        atomic_bit_write(&target, 4, 12, &source, source.len(), Ordering::Release);
+        assert_eq!(bytes(&target), [0b11111010, 0b00000101, 0b11111111]);
+    }
+
+    #[test]
+    fn check_words() {
+        let target = [AtomicU64::new(u64::MAX), AtomicU64::new(0)];
+        let source = 0xABCDu64.to_be_bytes();
+
+        atomic_bit_write(&target, 56, 16, &source, source.len(), Ordering::SeqCst);
+        assert_eq!(target[0].load(Ordering::Relaxed), 0xFFFF_FFFF_FFFF_FFAB);
+        assert_eq!(target[1].load(Ordering::Relaxed), 0xCD00_0000_0000_0000);
+    }
+
+    #[test]
+    fn check_concurrent_neighbours() {
+        let target: Vec<AtomicU64> = (0..4).map(|_| AtomicU64::new(0)).collect();
+
+        // 5 bit fields, so many of them cross a word boundary
+        std::thread::scope(|scope| {
+            for field in 0..51 {
+                let target = &target;
+                scope.spawn(move || {
+                    for round in 0..200u64 {
+                        let source = (round % 32).to_be_bytes();
+                        atomic_bit_write(target, field * 5, 5, &source, 8, Ordering::Relaxed);
+                    }
+                    let source = 0b10101u64.to_be_bytes();
+                    atomic_bit_write(target, field * 5, 5, &source, 8, Ordering::Relaxed);
+                });
+            }
+        });
+
+        for field in 0..51 {
+            let mut target_bits = [0u8; 1];
+            atomic_bit_read(
+                &target,
+                field * 5,
+                5,
+                &mut target_bits,
+                1,
+                Ordering::Relaxed,
+            );
+            assert_eq!(target_bits, [0b10101]);
+        }
+    }
+}
+
+/// Same as [`bit_clean`](crate::bit_clean), but for a shared buffer
+/// of atomic words. `order` is used for every update of a word
+pub fn atomic_bit_clean<W>(target: &[W], bit_offset: usize, bit_size: usize, order: Ordering)
+where
+    W: AtomicWord,
+{
+    for_each_word::<W>(bit_offset, bit_size, |index, from, to, _| {
+        target[index].fetch_and(!word_mask::<W>(from, to), order);
+    });
+}
+
+#[cfg(test)]
+mod tests_atomic_bit_clean {
+    use super::*;
+
+    #[test]
+    fn check_bytes() {
+        let target: Vec<AtomicU8> = (0..3).map(|_| AtomicU8::new(0b11111111)).collect();
+        atomic_bit_clean(&target, 5, 12, Ordering::AcqRel);
+
+        let bytes: Vec<u8> = target
+            .iter()
+            .map(|byte| byte.load(Ordering::Relaxed))
+            .collect();
+        assert_eq!(bytes, [0b11111000, 0b00000000, 0b01111111]);
+    }
+
+    #[test]
+    fn check_words() {
+        let target = [AtomicU64::new(u64::MAX), AtomicU64::new(u64::MAX)];
+        atomic_bit_clean(&target, 60, 8, Ordering::Relaxed);
+        assert_eq!(target[0].load(Ordering::Relaxed), 0xFFFF_FFFF_FFFF_FFF0);
+        assert_eq!(target[1].load(Ordering::Relaxed), 0x0FFF_FFFF_FFFF_FFFF);
+    }
+}
+
+/// Same as [`bit_read`], but for a shared buffer of atomic words.
+/// Every word is loaded once with `order`
+///
+/// **PANIC**: If requested bit_size large than target bit size, or
+/// `order` is not valid for a load (`Release`, `AcqRel`)
+///
+/// **NOTE**: Bits that are spread over several words are not read
+/// as one atomic snapshot
+pub fn atomic_bit_read<W, T>(
+    source: &[W],
+    source_bit_offset: usize,
+    recordable_bit_size: usize,
+    target: &mut T,
+    byte_target_len: usize,
+    order: Ordering,
+) where
+    W: AtomicWord,
+    T: IndexMut<usize, Output = u8>,
+{
+    assert!(
+        recordable_bit_size <= byte_target_len * 8,
+        "recordable_bit_size large than target bit size"
+    );
+
+    // Offset of the read bits in the target (they are the least significant)
+    let target_bit_offset = byte_target_len * 8 - recordable_bit_size;
+
+    for_each_word::<W>(
+        source_bit_offset,
+        recordable_bit_size,
+        |index, from, to, done| {
+            let word = source[index].load(order);
+            let value = (word & word_mask::<W>(from, to)) >> (W::BITS - to);
+
+            let chunk = value.to_be_bytes();
+            bit_write(
+                target,
+                target_bit_offset + done,
+                to - from,
+                &chunk,
+                chunk.len(),
+            );
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests_atomic_bit_read {
+    use super::*;
+
+    #[test]
+    fn check_bytes() {
+        let source: Vec<AtomicU8> = [0b00000111u8, 0b11111111, 0b00000000]
+            .into_iter()
+            .map(AtomicU8::new)
+            .collect();
+        let mut target = [0u8; 2];
+        atomic_bit_read(&source, 5, 11, &mut target, 2, Ordering::Acquire);
+        assert_eq!(target, [0b00000111, 0b11111111]);
+    }
+
+    #[test]
+    fn check_words() {
+        let source = [AtomicU64::new(0xFF), AtomicU64::new(0xA000_0000_0000_0000)];
+        let mut target = [0u8; 2];
+        atomic_bit_read(&source, 60, 8, &mut target, 2, Ordering::SeqCst);
+        assert_eq!(target, [0b00000000, 0b11111010]);
+    }
+}
+
+// Calls `f(word_index, from, to, done)` for every word affected by the
+// range, where `from..to` are the affected bits of the word and `done`
+// is the number of bits of the range before the word
+fn for_each_word<W: AtomicWord>(
+    bit_offset: usize,
+    bit_size: usize,
+    mut f: impl FnMut(usize, usize, usize, usize),
+) {
+    let mut done = 0;
+    while done < bit_size {
+        let bit_index = bit_offset + done;
+        let from = bit_index % W::BITS;
+        let to = (from + bit_size - done).min(W::BITS);
+        f(bit_index / W::BITS, from, to, done);
+        done += to - from;
+    }
+}
+
+// Mask of the bits `from..to` of a word, counting from the most significant
+fn word_mask<W: AtomicWord>(from: usize, to: usize) -> u64 {
+    let ones = u64::MAX >> (64 - W::BITS);
+    let ahead = ones.checked_shr(from as u32).unwrap_or_default();
+    let behind = ones.checked_shr(to as u32).unwrap_or_default();
+    ahead & !behind
+}
+
+// Replaces the bits under `mask` of the word with `value`
+// by a compare-and-swap loop
+fn update<W: AtomicWord>(word: &W, mask: u64, value: u64, order: Ordering) {
+    let failure = match order {
+        Ordering::Release | Ordering::Relaxed => Ordering::Relaxed,
+        Ordering::Acquire | Ordering::AcqRel => Ordering::Acquire,
+        _ => Ordering::SeqCst,
+    };
+
+    let mut current = word.load(failure);
+    loop {
+        let new = (current & !mask) | (value & mask);
+        match word.compare_exchange_weak(current, new, order, failure) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::ops::Index;
+
+use crate::int::max_hold;
+use crate::slice::{BitSlice, BitSliceMut};
+use crate::{bit_clean, bit_write, membitcpy, membitmove};
+
+/// What the unused bits of the last byte are filled with,
+/// when a [`BitBuf`] is converted to bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    #[default]
+    Zeros,
+    Ones,
+}
+
+/// Owned bit buffer, that grows as bits are appended and
+/// knows its exact length in bits
+///
+/// **NOTE**: Bits of the last byte that are out of the length are
+/// always zero
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitBuf {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+
+impl BitBuf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty buffer with space for at least `bit_capacity` bits
+    pub fn with_capacity(bit_capacity: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(bit_capacity.div_ceil(8)),
+            bit_len: 0,
+        }
+    }
+
+    /// Buffer holding all bits of `bytes`
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            bit_len: bytes.len() * 8,
+            bytes,
+        }
+    }
+
+    /// Length of the buffer in bits
+    pub fn len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bit_len == 0
+    }
+
+    /// Length of the buffer in bytes (rounding up)
+    pub fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Underlying bytes, the unused bits of the last byte are zero
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Bit at `index`, `None` if out of the buffer
+    pub fn get(&self, index: usize) -> Option<bool> {
+        self.as_bit_slice().get(index)
+    }
+
+    pub fn as_bit_slice(&self) -> BitSlice<'_, Vec<u8>> {
+        BitSlice::new(&self.bytes, 0, self.bit_len)
+    }
+
+    pub fn as_bit_slice_mut(&mut self) -> BitSliceMut<'_, Vec<u8>> {
+        BitSliceMut::new(&mut self.bytes, 0, self.bit_len)
+    }
+
+    /// Appends one bit
+    pub fn push(&mut self, bit: bool) {
+        self.push_bits(bit as u64, 1);
+    }
+
+    /// Appends `width` least significant bits of `value`, the most
+    /// significant bits that are out of `width` are discarded
+    ///
+    /// **PANIC**: If `width` large than 64
+    pub fn push_bits(&mut self, value: u64, width: usize) {
+        // Checked before growing, so the buffer stays valid after the panic
+        assert!(width <= 64, "width large than 64");
+
+        let source = value.to_be_bytes();
+        let offset = self.grow(width);
+        bit_write(&mut self.bytes, offset, width, &source, source.len());
+    }
+
+    /// Appends all bits of `source`
+    pub fn extend_from_bitslice<S>(&mut self, source: &BitSlice<'_, S>)
+    where
+        S: Index<usize, Output = u8>,
+    {
+        let offset = self.grow(source.len());
+        membitcpy(
+            &mut self.bytes,
+            offset,
+            source.len(),
+            source.storage(),
+            source.bit_offset(),
+        );
+    }
+
+    /// Shortens the buffer to `bit_len` bits, has no effect
+    /// if the buffer is already shorter
+    pub fn truncate(&mut self, bit_len: usize) {
+        if bit_len >= self.bit_len {
+            return;
+        }
+        bit_clean(&mut self.bytes, bit_len, self.bit_len - bit_len);
+        self.bytes.truncate(bit_len.div_ceil(8));
+        self.bit_len = bit_len;
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.bit_len = 0;
+    }
+
+    /// Converts to bytes, filling the unused bits of the last byte
+    /// according to `padding`
+    pub fn into_vec(mut self, padding: Padding) -> Vec<u8> {
+        let unused = self.bytes.len() * 8 - self.bit_len;
+        if padding == Padding::Ones {
+            if let Some(last) = self.bytes.last_mut() {
+                *last |= max_hold::<u8>(unused as u32);
+            }
+        }
+        self.bytes
+    }
+
+    // Extends the length by `bit_size` zero bits,
+    // returns the offset of the new bits
+    fn grow(&mut self, bit_size: usize) -> usize {
+        let offset = self.bit_len;
+        self.bit_len += bit_size;
+        self.bytes.resize(self.bit_len.div_ceil(8), 0);
+        offset
+    }
+}
+
+impl fmt::Debug for BitBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BitBuf{:?}", self.as_bit_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests_bit_buf {
+    use super::*;
+
+    #[test]
+    fn check_push_bits() {
+        let mut buf = BitBuf::new();
+        buf.push_bits(0b101, 3);
+        buf.push(true);
+        buf.push_bits(0xABCD_EF01_2345, 48);
+
+        assert_eq!(buf.len(), 52);
+        assert_eq!(buf.byte_len(), 7);
+        assert_eq!(
+            buf.as_bytes(),
+            [0b10111010, 0b10111100, 0b11011110, 0b11110000, 0b00010010, 0b00110100, 0b01010000]
+        );
+    }
+
+    #[test]
+    fn check_push_too_wide() {
+        let mut buf = BitBuf::new();
+        buf.push_bits(0b101, 3);
+
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| buf.push_bits(0, 65)));
+        assert!(result.is_err());
+        assert_eq!(buf.len(), 3);
+        assert_eq!(buf.as_bytes(), [0b10100000]);
+    }
+
+    #[test]
+    fn check_extend_from_bitslice() {
+        let source = [0b00111100u8, 0b11000000u8];
+        let mut buf = BitBuf::new();
+        buf.push_bits(0b1, 1);
+        buf.extend_from_bitslice(&BitSlice::new(&source, 2, 8));
+        buf.extend_from_bitslice(&BitSlice::new(&source, 0, 0));
+
+        assert_eq!(buf.len(), 9);
+        assert_eq!(buf.as_bytes(), [0b11111001, 0b10000000]);
+    }
+
+    #[test]
+    fn check_truncate() {
+        let mut buf = BitBuf::from_bytes(vec![0b11111111, 0b11111111]);
+        buf.truncate(20);
+        assert_eq!(buf.len(), 16);
+
+        buf.truncate(5);
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf.as_bytes(), [0b11111000]);
+
+        buf.push_bits(0, 3);
+        assert_eq!(buf.as_bytes(), [0b11111000]);
+        assert_eq!(format!("{:?}", buf), "BitBuf[11111000]");
+    }
+
+    #[test]
+    fn check_into_vec() {
+        let mut buf = BitBuf::new();
+        buf.push_bits(0b0101, 4);
+        assert_eq!(buf.clone().into_vec(Padding::Zeros), [0b01010000]);
+        assert_eq!(buf.clone().into_vec(Padding::Ones), [0b01011111]);
+
+        buf.push_bits(0b0101, 4);
+        assert_eq!(buf.into_vec(Padding::Ones), [0b01010101]);
+    }
+}
+
+/// Inserts `bit_size` bits of source at `source_bit_offset` into
+/// the buffer at `at_bit`, the bits after it are shifted to the end
+///
+/// **PANIC**: If `at_bit` large than length of the buffer,
+/// or out of range source
+pub fn bit_insert<S>(
+    buf: &mut BitBuf,
+    at_bit: usize,
+    source: &S,
+    source_bit_offset: usize,
+    bit_size: usize,
+) where
+    S: Index<usize, Output = u8>,
+{
+    assert!(at_bit <= buf.bit_len, "at_bit out of the buffer");
+
+    let tail = buf.bit_len - at_bit;
+    buf.grow(bit_size);
+    membitmove(&mut buf.bytes, at_bit + bit_size, tail, at_bit);
+
+    bit_clean(&mut buf.bytes, at_bit, bit_size);
+    membitcpy(&mut buf.bytes, at_bit, bit_size, source, source_bit_offset);
+}
+
+#[cfg(test)]
+mod tests_bit_insert {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_middle() {
+        let mut buf = BitBuf::new();
+        buf.push_bits(0b11_1100_0011, 10);
+        bit_insert(&mut buf, 4, &[0b00101000u8], 2, 3);
+
+        assert_eq!(buf.len(), 13);
+        assert_eq!(buf.as_bytes(), [0b11111010, 0b00011000]);
+    }
+
+    #[test]
+    fn check_edges() {
+        let mut buf = BitBuf::from_bytes(vec![0b10000001]);
+        bit_insert(&mut buf, 0, &[0b11000000u8], 0, 2);
+        bit_insert(&mut buf, 10, &[0b01000000u8], 0, 2);
+
+        assert_eq!(buf.len(), 12);
+        assert_eq!(buf.as_bytes(), [0b11100000, 0b01010000]);
+    }
+
+    #[test]
+    fn check_long() {
+        let source = test_bytes(20, 0);
+        let mut buf = BitBuf::from_bytes(vec![0b11111111; 10]);
+        bit_insert(&mut buf, 7, &source, 3, 150);
+
+        let mut expected = BitBuf::new();
+        expected.push_bits(0b1111111, 7);
+        expected.extend_from_bitslice(&BitSlice::new(&source, 3, 150));
+        expected.push_bits(u64::MAX, 64);
+        expected.push_bits(0b1_1111_1111, 9);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_out_of_range() {
+        let mut buf = BitBuf::from_bytes(vec![0]);
+        bit_insert(&mut buf, 9, &[0u8], 0, 1);
+    }
+}
+
+/// Removes `bit_size` bits of the buffer at `at_bit`,
+/// the bits after them are shifted to the start
+///
+/// **PANIC**: If the removed bits are out of the buffer
+pub fn bit_remove(buf: &mut BitBuf, at_bit: usize, bit_size: usize) {
+    assert!(
+        at_bit + bit_size <= buf.bit_len,
+        "removed bits out of the buffer"
+    );
+
+    let tail = buf.bit_len - at_bit - bit_size;
+    membitmove(&mut buf.bytes, at_bit, tail, at_bit + bit_size);
+    buf.truncate(buf.bit_len - bit_size);
+}
+
+#[cfg(test)]
+mod tests_bit_remove {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_middle() {
+        let mut buf = BitBuf::from_bytes(vec![0b11111010, 0b00011000]);
+        bit_remove(&mut buf, 4, 3);
+
+        assert_eq!(buf.len(), 13);
+        assert_eq!(buf.as_bytes(), [0b11110000, 0b11000000]);
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let source = test_bytes(20, 0);
+        let origin = BitBuf::from_bytes((0..12).map(|index| index * 7).collect());
+
+        let mut buf = origin.clone();
+        bit_insert(&mut buf, 45, &source, 1, 77);
+        bit_remove(&mut buf, 45, 77);
+        assert_eq!(buf, origin);
+
+        let bit_len = buf.len();
+        bit_remove(&mut buf, 0, bit_len);
+        assert!(buf.is_empty());
+        assert_eq!(buf.byte_len(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_out_of_range() {
+        let mut buf = BitBuf::from_bytes(vec![0]);
+        bit_remove(&mut buf, 5, 4);
+    }
+}
@@ -0,0 +1,213 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut, Range};
+
+use crate::{bit_clean, bit_write, membitcpy};
+
+/// Error of [`CheckedWriter`], when a write touches already written bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlapError {
+    /// Offset of the rejected write in the target
+    pub bit_offset: usize,
+    /// Size of the rejected write
+    pub bit_size: usize,
+    /// First run of already written bits, that the write touches
+    pub overlap: Range<usize>,
+}
+
+impl fmt::Display for OverlapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: write of {} bits at offset {} overlaps already written bits {}..{}.",
+            self.bit_size, self.bit_offset, self.overlap.start, self.overlap.end
+        )
+    }
+}
+
+impl Error for OverlapError {}
+
+/// Writer that rejects writes to target bits that were already written.
+///
+/// Since [`bit_write`] and [`membitcpy`] OR into the target, overlapping
+/// fields silently produce garbage. This wrapper tracks written bits, when
+/// debug assertions or the `checked-write` feature are enabled. Otherwise
+/// it does no tracking and every write succeeds
+pub struct CheckedWriter<'t, T> {
+    target: &'t mut T,
+    // Bitmap of already written bits of the target, grows on demand
+    #[cfg(any(debug_assertions, feature = "checked-write"))]
+    written: Vec<u8>,
+}
+
+impl<'t, T> CheckedWriter<'t, T>
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    /// Starts tracking of writes to `target`
+    ///
+    /// **NOTE**: Bits that are already set in the target are not
+    /// considered written
+    pub fn new(target: &'t mut T) -> Self {
+        Self {
+            target,
+            #[cfg(any(debug_assertions, feature = "checked-write"))]
+            written: vec![],
+        }
+    }
+
+    /// Whether writes are actually tracked in the current build
+    pub const fn is_tracking() -> bool {
+        cfg!(any(debug_assertions, feature = "checked-write"))
+    }
+
+    /// Returns the wrapped target
+    pub fn into_inner(self) -> &'t mut T {
+        self.target
+    }
+
+    /// Same as [`bit_write`], but fails without writing
+    /// if the range was already written
+    pub fn bit_write<S>(
+        &mut self,
+        target_bit_offset: usize,
+        recordable_bit_size: usize,
+        source: &S,
+        byte_source_len: usize,
+    ) -> Result<(), OverlapError>
+    where
+        S: Index<usize, Output = u8>,
+    {
+        self.mark_written(target_bit_offset, recordable_bit_size)?;
+        bit_write(
+            self.target,
+            target_bit_offset,
+            recordable_bit_size,
+            source,
+            byte_source_len,
+        );
+        Ok(())
+    }
+
+    /// Same as [`membitcpy`], but fails without writing
+    /// if the range was already written
+    pub fn membitcpy<S>(
+        &mut self,
+        target_bit_offset: usize,
+        recordable_bit_size: usize,
+        source: &S,
+        source_bit_offset: usize,
+    ) -> Result<(), OverlapError>
+    where
+        S: Index<usize, Output = u8>,
+    {
+        self.mark_written(target_bit_offset, recordable_bit_size)?;
+        membitcpy(
+            self.target,
+            target_bit_offset,
+            recordable_bit_size,
+            source,
+            source_bit_offset,
+        );
+        Ok(())
+    }
+
+    /// Same as [`bit_clean`], the cleaned range can be written again
+    pub fn bit_clean(&mut self, bit_offset: usize, bit_size: usize) {
+        bit_clean(self.target, bit_offset, bit_size);
+
+        #[cfg(any(debug_assertions, feature = "checked-write"))]
+        for index in bit_offset..bit_offset + bit_size {
+            if let Some(byte) = self.written.get_mut(index / 8) {
+                *byte &= !(0b10000000 >> (index % 8));
+            }
+        }
+    }
+
+    #[cfg(any(debug_assertions, feature = "checked-write"))]
+    fn mark_written(&mut self, bit_offset: usize, bit_size: usize) -> Result<(), OverlapError> {
+        let is_written = |written: &[u8], index: usize| {
+            written
+                .get(index / 8)
+                .is_some_and(|byte| byte & (0b10000000 >> (index % 8)) != 0)
+        };
+
+        let range = bit_offset..bit_offset + bit_size;
+        if let Some(start) = range
+            .clone()
+            .find(|index| is_written(&self.written, *index))
+        {
+            let end = (start..range.end)
+                .find(|index| !is_written(&self.written, *index))
+                .unwrap_or(range.end);
+            return Err(OverlapError {
+                bit_offset,
+                bit_size,
+                overlap: start..end,
+            });
+        }
+
+        if self.written.len() < range.end.div_ceil(8) {
+            self.written.resize(range.end.div_ceil(8), 0);
+        }
+        for index in range {
+            self.written[index / 8] |= 0b10000000 >> (index % 8);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(debug_assertions, feature = "checked-write")))]
+    fn mark_written(&mut self, _bit_offset: usize, _bit_size: usize) -> Result<(), OverlapError> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, any(debug_assertions, feature = "checked-write")))]
+mod tests_checked_writer {
+    use super::*;
+
+    #[test]
+    fn check_disjoint() {
+        let mut target = [0u8; 2];
+        let mut writer = CheckedWriter::new(&mut target);
+
+        writer.bit_write(0, 6, &[0b00111111u8], 1).unwrap();
+        writer.bit_write(6, 4, &[0b00001001u8], 1).unwrap();
+        writer.membitcpy(10, 6, &[0b11111100u8], 0).unwrap();
+
+        assert_eq!(target, [0b11111110, 0b01111111]);
+    }
+
+    #[test]
+    fn check_overlap() {
+        let mut target = [0u8; 2];
+        let mut writer = CheckedWriter::new(&mut target);
+
+        writer.bit_write(4, 6, &[0b00111111u8], 1).unwrap();
+        let error = writer.bit_write(8, 4, &[0b00001111u8], 1).unwrap_err();
+        assert_eq!(
+            error,
+            OverlapError {
+                bit_offset: 8,
+                bit_size: 4,
+                overlap: 8..10,
+            }
+        );
+
+        // Rejected write does not touch the target
+        assert_eq!(target, [0b00001111, 0b11000000]);
+    }
+
+    #[test]
+    fn check_rewrite_after_clean() {
+        let mut target = [0u8; 1];
+        let mut writer = CheckedWriter::new(&mut target);
+
+        writer.bit_write(2, 3, &[0b00000111u8], 1).unwrap();
+        assert!(writer.membitcpy(0, 3, &[0b10100000u8], 0).is_err());
+
+        writer.bit_clean(2, 3);
+        writer.membitcpy(0, 3, &[0b10100000u8], 0).unwrap();
+        assert_eq!(target, [0b10100000]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::cmp::Ordering;
+use std::hash::Hasher;
+use std::ops::Index;
+
+use crate::read_bits;
+use crate::slice::get_bit;
+
+/// Compares `bit_size` bits of `a` at `a_bit_offset` with `bit_size`
+/// bits of `b` at `b_bit_offset`, bit by bit from the first one
+/// (like `memcmp`, but for bits)
+///
+/// **PANIC**: If out of range `a` or `b`
+pub fn membitcmp<A, B>(
+    a: &A,
+    a_bit_offset: usize,
+    b: &B,
+    b_bit_offset: usize,
+    bit_size: usize,
+) -> Ordering
+where
+    A: Index<usize, Output = u8> + ?Sized,
+    B: Index<usize, Output = u8> + ?Sized,
+{
+    match first_difference(a, a_bit_offset, b, b_bit_offset, bit_size) {
+        None => Ordering::Equal,
+        // The first different bit decides, set bit is greater
+        Some(index) if get_bit(a, a_bit_offset + index) => Ordering::Greater,
+        Some(_) => Ordering::Less,
+    }
+}
+
+#[cfg(test)]
+mod tests_membitcmp {
+    use super::*;
+
+    #[test]
+    fn check_order() {
+        let a = [0b00010110u8, 0b11000000u8];
+        let b = [0b10110100u8];
+
+        assert_eq!(membitcmp(&a, 3, &b, 0, 6), Ordering::Equal);
+        assert_eq!(membitcmp(&a, 3, &b, 0, 7), Ordering::Greater);
+        assert_eq!(membitcmp(&b, 0, &a, 3, 7), Ordering::Less);
+        assert_eq!(membitcmp(&a, 0, &b, 0, 0), Ordering::Equal);
+    }
+
+    #[test]
+    fn check_unsized() {
+        let a = [0b00010110u8, 0b11000000u8];
+        let b = [0b10110100u8];
+        assert_eq!(membitcmp(&a[..], 3, &b[..], 0, 7), Ordering::Greater);
+    }
+}
+
+/// Whether `bit_size` bits of `a` at `a_bit_offset` are equal to
+/// `bit_size` bits of `b` at `b_bit_offset`. Compares whole bytes
+/// when both offsets are byte aligned
+///
+/// **PANIC**: If out of range `a` or `b`
+pub fn membiteq<A, B>(
+    a: &A,
+    a_bit_offset: usize,
+    b: &B,
+    b_bit_offset: usize,
+    bit_size: usize,
+) -> bool
+where
+    A: Index<usize, Output = u8> + ?Sized,
+    B: Index<usize, Output = u8> + ?Sized,
+{
+    if a_bit_offset.is_multiple_of(8) && b_bit_offset.is_multiple_of(8) {
+        let a_start = a_bit_offset / 8;
+        let b_start = b_bit_offset / 8;
+        let whole_bytes = bit_size / 8;
+        if (0..whole_bytes).any(|index| a[a_start + index] != b[b_start + index]) {
+            return false;
+        }
+
+        // Only the bits of the last partial byte are left
+        let done = whole_bytes * 8;
+        return first_difference(
+            a,
+            a_bit_offset + done,
+            b,
+            b_bit_offset + done,
+            bit_size - done,
+        )
+        .is_none();
+    }
+
+    first_difference(a, a_bit_offset, b, b_bit_offset, bit_size).is_none()
+}
+
+#[cfg(test)]
+mod tests_membiteq {
+    use super::*;
+
+    #[test]
+    fn check_aligned() {
+        let a = [0b11001100u8, 0b10101010u8, 0b11110000u8];
+        let b = [0b00000000u8, 0b11001100u8, 0b10101010u8, 0b11111111u8];
+
+        assert!(membiteq(&a, 0, &b, 8, 20));
+        assert!(!membiteq(&a, 0, &b, 8, 21));
+    }
+
+    #[test]
+    fn check_unaligned() {
+        let a = [0b00000110u8, 0b01101010u8];
+        let b = [0b11001101u8, 0b01000000u8];
+
+        assert!(membiteq(&a, 5, &b, 0, 11));
+        assert!(!membiteq(&a, 4, &b, 0, 11));
+    }
+}
+
+/// Index of the first bit (counting from the offsets) that differs
+/// between the ranges, `None` if the ranges are equal
+///
+/// **PANIC**: If out of range `a` or `b`
+pub fn first_difference<A, B>(
+    a: &A,
+    a_bit_offset: usize,
+    b: &B,
+    b_bit_offset: usize,
+    bit_size: usize,
+) -> Option<usize>
+where
+    A: Index<usize, Output = u8> + ?Sized,
+    B: Index<usize, Output = u8> + ?Sized,
+{
+    // Compare by chunks no longer than a `u64`
+    let mut done = 0;
+    while done < bit_size {
+        let width = (bit_size - done).min(64);
+        let a_chunk = read_bits(a, a_bit_offset + done, width);
+        let b_chunk = read_bits(b, b_bit_offset + done, width);
+
+        // Chunks are right aligned, so skip the ahead empty bits
+        let difference = a_chunk ^ b_chunk;
+        if difference != 0 {
+            let ahead_empty_bit = 64 - width;
+            return Some(done + difference.leading_zeros() as usize - ahead_empty_bit);
+        }
+        done += width;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests_first_difference {
+    use super::*;
+
+    #[test]
+    fn check_small() {
+        let a = [0b10110000u8];
+        let b = [0b10100000u8];
+        assert_eq!(first_difference(&a, 0, &b, 0, 8), Some(3));
+        assert_eq!(first_difference(&a, 0, &b, 0, 3), None);
+    }
+
+    #[test]
+    fn check_long() {
+        let a = [0b11111111u8; 20];
+        let mut b = [0b11111111u8; 21];
+        b[16] = 0b11111011;
+
+        assert_eq!(first_difference(&a, 0, &b, 0, 160), Some(16 * 8 + 5));
+        assert_eq!(first_difference(&a, 3, &b, 3, 131), Some(16 * 8 + 5 - 3));
+        assert_eq!(first_difference(&a, 3, &b, 11, 150), Some(16 * 8 + 5 - 11));
+        assert_eq!(first_difference(&a, 0, &b, 0, 16 * 8 + 5), None);
+    }
+}
+
+/// Feeds `bit_size` bits of `source` at `bit_offset` into `state`.
+/// The result depends only on the bits, not on their offset, so equal
+/// ranges at different offsets give equal hashes
+///
+/// **PANIC**: If out of range source
+pub fn bit_hash<S, H>(source: &S, bit_offset: usize, bit_size: usize, state: &mut H)
+where
+    S: Index<usize, Output = u8> + ?Sized,
+    H: Hasher,
+{
+    state.write_usize(bit_size);
+
+    let mut done = 0;
+    while done < bit_size {
+        let width = (bit_size - done).min(64);
+        state.write_u64(read_bits(source, bit_offset + done, width));
+        done += width;
+    }
+}
+
+#[cfg(test)]
+mod tests_bit_hash {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash<S>(source: &S, bit_offset: usize, bit_size: usize) -> u64
+    where
+        S: Index<usize, Output = u8>,
+    {
+        let mut state = DefaultHasher::new();
+        bit_hash(source, bit_offset, bit_size, &mut state);
+        state.finish()
+    }
+
+    #[test]
+    fn check_offset_independent() {
+        let a = [0b00000101u8, 0b11001110u8, 0b10000000u8];
+        let b = [0b10111001u8, 0b11010000u8];
+
+        assert_eq!(hash(&a, 5, 12), hash(&b, 0, 12));
+        assert_ne!(hash(&a, 5, 12), hash(&b, 0, 11));
+        assert_ne!(hash(&a, 5, 12), hash(&b, 1, 12));
+    }
+}
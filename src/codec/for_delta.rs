@@ -0,0 +1,337 @@
+//! Frame-of-reference and delta bit-packing of integer columns
+//!
+//! Values are split into blocks of [`BLOCK_LEN`]. Every block is stored either
+//! as offsets from the block minimum (frame of reference), or as differences
+//! of neighbouring values, that are stored as offsets from the minimum
+//! difference (delta). The encoder picks the smaller kind for every block,
+//! the offsets are bit-packed with the minimal width
+//!
+//! ```text
+//! column: | len width (7) | len |  block  |  block  | ...
+//! FOR:    | 0 | width (7) | base width (7) | base | offsets (len * width) |
+//! delta:  | 1 | width (7) | first width (7) | first |
+//!             | min width (7) | min (zigzag) | offsets ((len - 1) * width) |
+//! ```
+//!
+//! Signed values can be encoded as `u64` casts, differences are
+//! computed with wrapping arithmetic
+
+use super::{push_with_width, width_of, BitReader, DecodeError};
+use crate::buf::{BitBuf, Padding};
+
+/// Number of values in a block, the last block can be shorter
+pub const BLOCK_LEN: usize = 128;
+
+// Kinds of blocks in the header
+const FRAME_OF_REFERENCE: u64 = 0;
+const DELTA: u64 = 1;
+
+/// Encodes `values` to bytes
+pub fn encode(values: &[u64]) -> Vec<u8> {
+    let mut buf = BitBuf::new();
+    push_with_width(&mut buf, values.len() as u64);
+    for block in values.chunks(BLOCK_LEN) {
+        encode_block(&mut buf, block);
+    }
+    buf.into_vec(Padding::Zeros)
+}
+
+/// Decodes all values of bytes produced by [`encode`]
+pub fn decode(bytes: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    Ok(Decoder::new(bytes)?.decode_all())
+}
+
+#[cfg(test)]
+mod tests_encode {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let values: Vec<u64> = (0..1000u64)
+            .map(|index| 1_000_000 + index * 7919 % 5000)
+            .collect();
+        assert_eq!(decode(&encode(&values)), Ok(values));
+
+        assert_eq!(decode(&encode(&[])), Ok(vec![]));
+        assert_eq!(decode(&encode(&[u64::MAX, 0])), Ok(vec![u64::MAX, 0]));
+    }
+
+    #[test]
+    fn check_frame_of_reference_size() {
+        // 128 values in 1000..1016 take 4 bits each
+        let values: Vec<u64> = (0..128u64).map(|index| 1000 + index % 16).collect();
+        let bytes = encode(&values);
+
+        // len + kind, width, base + values
+        let bit_len = (7 + 8) + (1 + 7 + 7 + 10) + 128 * 4;
+        assert_eq!(bytes.len(), (bit_len as usize).div_ceil(8));
+    }
+
+    #[test]
+    fn check_delta_size() {
+        // Timestamps in milliseconds with a step of about a second
+        let values: Vec<u64> = (0..128u64)
+            .map(|index| 1_700_000_000_000 + index * 1000 + index % 4)
+            .collect();
+        let bytes = encode(&values);
+
+        // Differences are 997 or 1001, their offsets from 997 take 3 bits
+        let bit_len = (7 + 8) + (1 + 7 + 7 + 41 + 7 + 11) + 127 * 3;
+        assert_eq!(bytes.len(), (bit_len as usize).div_ceil(8));
+        assert_eq!(decode(&bytes), Ok(values));
+    }
+
+    #[test]
+    fn check_decreasing() {
+        let values: Vec<u64> = (0..300i64).map(|index| (5 - index * 3) as u64).collect();
+        assert_eq!(decode(&encode(&values)), Ok(values));
+    }
+
+    #[test]
+    fn check_truncated() {
+        let values: Vec<u64> = (0..200u64).collect();
+        let bytes = encode(&values);
+        assert!(decode(&bytes[..bytes.len() - 2]).is_err());
+        assert!(decode(&[]).is_err());
+
+        // Length of 2^60 values in 8 bytes
+        let mut buf = BitBuf::new();
+        push_with_width(&mut buf, 1 << 60);
+        buf.push_bits(0, 64);
+        assert!(decode(&buf.into_vec(Padding::Zeros)).is_err());
+    }
+}
+
+/// Decoder of bytes produced by [`encode`] with random access to blocks.
+/// Headers of all blocks are checked on creation, so the blocks are
+/// decoded without errors
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    len: usize,
+    // Offsets of the blocks
+    block_offsets: Vec<usize>,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = BitReader::new(bytes, 0);
+        let len = reader.read_with_width()? as usize;
+
+        // Every block takes at least its kind and two widths
+        let block_count = len.div_ceil(BLOCK_LEN);
+        if block_count > reader.remaining() / (1 + 7 + 7) {
+            return Err(DecodeError {
+                bit_offset: reader.bit_offset(),
+            });
+        }
+        let mut block_offsets = Vec::with_capacity(block_count);
+        for index in 0..block_count {
+            block_offsets.push(reader.bit_offset());
+            let header = read_header(&mut reader)?;
+            reader.skip(header.width * header.offsets_len(block_len(len, index)))?;
+        }
+
+        Ok(Self {
+            bytes,
+            len,
+            block_offsets,
+        })
+    }
+
+    /// Number of values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.block_offsets.len()
+    }
+
+    /// Decodes the block at `index`, that holds values
+    /// `index * BLOCK_LEN..(index + 1) * BLOCK_LEN`
+    ///
+    /// **PANIC**: If `index` out of the blocks
+    pub fn decode_block(&self, index: usize) -> Vec<u64> {
+        let mut reader = BitReader::new(self.bytes, self.block_offsets[index]);
+        let header = read_header(&mut reader).unwrap();
+        let len = block_len(self.len, index);
+
+        let mut values = Vec::with_capacity(len);
+        match header.kind {
+            FRAME_OF_REFERENCE => {
+                for _ in 0..len {
+                    values.push(header.base.wrapping_add(reader.read(header.width).unwrap()));
+                }
+            }
+            _ => {
+                let mut value = header.base;
+                values.push(value);
+                for _ in 1..len {
+                    let delta = header
+                        .min_delta
+                        .wrapping_add(reader.read(header.width).unwrap());
+                    value = value.wrapping_add(delta);
+                    values.push(value);
+                }
+            }
+        }
+        values
+    }
+
+    /// Value at `index`, `None` if out of the values. Only the block of
+    /// the value is decoded, for a frame of reference block only the value
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        let block_index = index / BLOCK_LEN;
+
+        let mut reader = BitReader::new(self.bytes, self.block_offsets[block_index]);
+        let header = read_header(&mut reader).unwrap();
+        if header.kind == FRAME_OF_REFERENCE {
+            reader.skip(header.width * (index % BLOCK_LEN)).unwrap();
+            return Some(header.base.wrapping_add(reader.read(header.width).unwrap()));
+        }
+        Some(self.decode_block(block_index)[index % BLOCK_LEN])
+    }
+
+    /// Decodes all blocks
+    pub fn decode_all(&self) -> Vec<u64> {
+        let mut values = Vec::with_capacity(self.len);
+        for index in 0..self.block_count() {
+            values.extend(self.decode_block(index));
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests_decoder {
+    use super::*;
+
+    #[test]
+    fn check_random_access() {
+        let values: Vec<u64> = (0..1000u64)
+            .map(|index| match index / BLOCK_LEN as u64 % 2 {
+                0 => index * 31 % 100,
+                _ => 1_000_000 + index * 10,
+            })
+            .collect();
+        let bytes = encode(&values);
+        let decoder = Decoder::new(&bytes).unwrap();
+
+        assert_eq!(decoder.len(), 1000);
+        assert_eq!(decoder.block_count(), 8);
+        assert_eq!(decoder.decode_block(3), values[384..512]);
+        assert_eq!(decoder.decode_block(7), values[896..]);
+        for index in [0, 127, 128, 129, 500, 999] {
+            assert_eq!(decoder.get(index), Some(values[index]));
+        }
+        assert_eq!(decoder.get(1000), None);
+    }
+
+    #[test]
+    fn check_constant_block() {
+        let values = [42u64; 200];
+        let bytes = encode(&values);
+        let decoder = Decoder::new(&bytes).unwrap();
+        assert_eq!(decoder.decode_all(), values);
+        assert_eq!(decoder.get(150), Some(42));
+    }
+}
+
+// Header of a block
+struct Header {
+    kind: u64,
+    width: usize,
+    // The minimum for a frame of reference block, the first value for delta
+    base: u64,
+    min_delta: u64,
+}
+
+impl Header {
+    // Number of packed offsets in a block of `len` values
+    fn offsets_len(&self, len: usize) -> usize {
+        match self.kind {
+            FRAME_OF_REFERENCE => len,
+            _ => len - 1,
+        }
+    }
+}
+
+fn read_header(reader: &mut BitReader<'_>) -> Result<Header, DecodeError> {
+    let kind = reader.read(1)?;
+    let width = reader.read_width()?;
+    let base = reader.read_with_width()?;
+    let min_delta = match kind {
+        DELTA => from_zigzag(reader.read_with_width()?),
+        _ => 0,
+    };
+    Ok(Header {
+        kind,
+        width,
+        base,
+        min_delta,
+    })
+}
+
+fn encode_block(buf: &mut BitBuf, block: &[u64]) {
+    let min = *block.iter().min().unwrap();
+    let width = width_of(block.iter().map(|value| value - min).max().unwrap());
+    let for_bit_len = 1 + 7 + 7 + width_of(min) + block.len() * width;
+
+    let deltas: Vec<i64> = block
+        .windows(2)
+        .map(|pair| pair[1].wrapping_sub(pair[0]) as i64)
+        .collect();
+    let min_delta = deltas.iter().copied().min().unwrap_or_default();
+    let delta_width = width_of(
+        deltas
+            .iter()
+            .map(|delta| delta.wrapping_sub(min_delta) as u64)
+            .max()
+            .unwrap_or_default(),
+    );
+    let delta_bit_len = 1
+        + 7
+        + 7
+        + width_of(block[0])
+        + 7
+        + width_of(to_zigzag(min_delta))
+        + deltas.len() * delta_width;
+
+    if for_bit_len <= delta_bit_len {
+        buf.push_bits(FRAME_OF_REFERENCE, 1);
+        buf.push_bits(width as u64, 7);
+        push_with_width(buf, min);
+        for value in block {
+            buf.push_bits(value - min, width);
+        }
+    } else {
+        buf.push_bits(DELTA, 1);
+        buf.push_bits(delta_width as u64, 7);
+        push_with_width(buf, block[0]);
+        push_with_width(buf, to_zigzag(min_delta));
+        for delta in deltas {
+            buf.push_bits(delta.wrapping_sub(min_delta) as u64, delta_width);
+        }
+    }
+}
+
+// Number of values in the block at `index`
+fn block_len(len: usize, index: usize) -> usize {
+    (len - index * BLOCK_LEN).min(BLOCK_LEN)
+}
+
+// Maps signed to unsigned so that values near zero are small
+fn to_zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn from_zigzag(value: u64) -> u64 {
+    (value >> 1) ^ (value & 1).wrapping_neg()
+}
@@ -0,0 +1,360 @@
+//! Gorilla compression of time series of `(timestamp, f64)` points
+//!
+//! Timestamps are stored as differences of neighbouring deltas
+//! (delta of delta), that are zero for regular series. Values are stored
+//! as XOR with the previous value, only the bits between the leading and
+//! the trailing zeros of XOR are written
+//!
+//! ```text
+//! delta of delta:
+//! 0                   zero
+//! 10      + 7 bits    -64..=63
+//! 110     + 9 bits    -256..=255
+//! 1110    + 12 bits   -2048..=2047
+//! 11110   + 32 bits   i32
+//! 111110  + 64 bits   other
+//! 111111              end of the stream
+//!
+//! value XOR:
+//! 0                                               zero
+//! 10  + meaningful bits                           in the previous window
+//! 11  + leading (5) + length (6, 0 for 64) + meaningful bits
+//! ```
+//!
+//! The first point is encoded as following a point `(0, 0.0)`
+//! with zero delta, so the stream has no header
+//!
+//! **NOTE**: Timestamps may decrease, deltas are computed with
+//! wrapping arithmetic
+
+use super::{BitReader, DecodeError};
+use crate::buf::{BitBuf, Padding};
+
+// Buckets of delta of delta: prefix, prefix width, value width
+const BUCKETS: [(u64, usize, usize); 5] = [
+    (0b10, 2, 7),
+    (0b110, 3, 9),
+    (0b1110, 4, 12),
+    (0b11110, 5, 32),
+    (0b111110, 6, 64),
+];
+const END: (u64, usize) = (0b111111, 6);
+
+// Leading zeros are stored in 5 bits
+const MAX_LEADING: u32 = 31;
+
+/// Encodes `points` at once
+pub fn encode(points: &[(u64, f64)]) -> Vec<u8> {
+    let mut encoder = Encoder::new();
+    for (timestamp, value) in points {
+        encoder.push(*timestamp, *value);
+    }
+    encoder.finish()
+}
+
+/// Decodes all points of bytes produced by [`encode`] or [`Encoder`]
+pub fn decode(bytes: &[u8]) -> Result<Vec<(u64, f64)>, DecodeError> {
+    Decoder::new(bytes).collect()
+}
+
+/// Streaming encoder, points are appended to a bit buffer as they come
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: BitBuf,
+    len: usize,
+    state: State,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of bits of the encoded points
+    pub fn bit_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Appends a point
+    pub fn push(&mut self, timestamp: u64, value: f64) {
+        let state = &mut self.state;
+        let delta = timestamp.wrapping_sub(state.timestamp);
+        push_delta_of_delta(&mut self.buf, delta.wrapping_sub(state.delta) as i64);
+        state.timestamp = timestamp;
+        state.delta = delta;
+
+        let xor = value.to_bits() ^ state.value;
+        state.value = value.to_bits();
+        if xor == 0 {
+            self.buf.push(false);
+            self.len += 1;
+            return;
+        }
+
+        let leading = xor.leading_zeros().min(MAX_LEADING);
+        let trailing = xor.trailing_zeros();
+        match state.window {
+            Some((window_leading, window_trailing))
+                if leading >= window_leading && trailing >= window_trailing =>
+            {
+                let length = 64 - window_leading - window_trailing;
+                self.buf.push_bits(0b10, 2);
+                self.buf.push_bits(xor >> window_trailing, length as usize);
+            }
+            _ => {
+                let length = 64 - leading - trailing;
+                self.buf.push_bits(0b11, 2);
+                self.buf.push_bits(leading as u64, 5);
+                self.buf.push_bits(length as u64, 6);
+                self.buf.push_bits(xor >> trailing, length as usize);
+                state.window = Some((leading, trailing));
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Appends the end of the stream and returns the bytes
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf.push_bits(END.0, END.1);
+        self.buf.into_vec(Padding::Zeros)
+    }
+}
+
+#[cfg(test)]
+mod tests_encoder {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let points: Vec<(u64, f64)> = (0..1000u64)
+            .map(|index| {
+                let jitter = [0, 0, 0, 1, 0, 130, 0, 5000][index as usize % 8];
+                let timestamp = 1_700_000_000 + index * 60 + jitter;
+                let value = match index % 5 {
+                    0 => 12.0,
+                    1 => (index as f64).sqrt(),
+                    2 => -0.0,
+                    _ => 12.5 + (index % 3) as f64,
+                };
+                (timestamp, value)
+            })
+            .collect();
+        let decoded = decode(&encode(&points)).unwrap();
+        assert_eq!(decoded.len(), points.len());
+        for (decoded, point) in decoded.iter().zip(&points) {
+            assert_eq!(decoded.0, point.0);
+            assert_eq!(decoded.1.to_bits(), point.1.to_bits());
+        }
+    }
+
+    #[test]
+    fn check_special_values() {
+        let values = [f64::NAN, f64::INFINITY, f64::MIN_POSITIVE, f64::MAX, 0.0];
+        let points: Vec<(u64, f64)> = (0..5u64)
+            .map(|index| (u64::MAX - index * 1000, values[index as usize]))
+            .collect();
+        let decoded = decode(&encode(&points)).unwrap();
+        for (decoded, point) in decoded.iter().zip(&points) {
+            assert_eq!(decoded.0, point.0);
+            assert_eq!(decoded.1.to_bits(), point.1.to_bits());
+        }
+        assert_eq!(decode(&encode(&[])), Ok(vec![]));
+    }
+
+    #[test]
+    fn check_regular_size() {
+        let mut encoder = Encoder::new();
+        encoder.push(1000, 1.0);
+        let first_bit_len = encoder.bit_len();
+        encoder.push(1060, 1.0);
+
+        // Delta of delta 60 - 1000 and the zero XOR
+        let second_bit_len = first_bit_len + (4 + 12) + 1;
+        assert_eq!(encoder.bit_len(), second_bit_len);
+        for index in 2..10 {
+            encoder.push(1000 + index * 60, 1.0);
+        }
+        // One bit for the timestamp and one for the value
+        assert_eq!(encoder.bit_len(), second_bit_len + 8 * 2);
+        assert_eq!(encoder.len(), 10);
+    }
+
+    #[test]
+    fn check_window_reuse() {
+        let mut encoder = Encoder::new();
+        encoder.push(0, 1.0);
+        let bit_len = encoder.bit_len();
+
+        // 1.0 ^ 1.5 has a new window of one bit, that is reused for 1.5 ^ 1.0
+        encoder.push(0, 1.5);
+        assert_eq!(encoder.bit_len(), bit_len + 1 + (2 + 5 + 6 + 1));
+        encoder.push(0, 1.0);
+        assert_eq!(
+            encoder.bit_len(),
+            bit_len + 1 + (2 + 5 + 6 + 1) + 1 + (2 + 1)
+        );
+    }
+}
+
+/// Streaming decoder of bytes produced by [`Encoder`], yields points
+/// until the end of the stream. After an error yields `None`
+pub struct Decoder<'a> {
+    reader: BitReader<'a>,
+    state: State,
+    done: bool,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            reader: BitReader::new(bytes, 0),
+            state: State::default(),
+            done: false,
+        }
+    }
+
+    // Reads the next point, `None` at the end of the stream
+    fn read_point(&mut self) -> Result<Option<(u64, f64)>, DecodeError> {
+        let Some(delta_of_delta) = read_delta_of_delta(&mut self.reader)? else {
+            return Ok(None);
+        };
+        let state = &mut self.state;
+        state.delta = state.delta.wrapping_add(delta_of_delta as u64);
+        state.timestamp = state.timestamp.wrapping_add(state.delta);
+
+        let bit_offset = self.reader.bit_offset();
+        if self.reader.read(1)? == 1 {
+            let (leading, trailing) = match (self.reader.read(1)?, state.window) {
+                (0, Some(window)) => window,
+                (0, None) => return Err(DecodeError { bit_offset }),
+                _ => {
+                    let leading = self.reader.read(5)? as u32;
+                    let length = match self.reader.read(6)? as u32 {
+                        0 => 64,
+                        length => length,
+                    };
+                    if leading + length > 64 {
+                        return Err(DecodeError { bit_offset });
+                    }
+                    state.window = Some((leading, 64 - leading - length));
+                    (leading, 64 - leading - length)
+                }
+            };
+            let length = 64 - leading - trailing;
+            state.value ^= self.reader.read(length as usize)? << trailing;
+        }
+        Ok(Some((state.timestamp, f64::from_bits(state.value))))
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = Result<(u64, f64), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let point = self.read_point();
+        if !matches!(point, Ok(Some(_))) {
+            self.done = true;
+        }
+        point.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests_decoder {
+    use super::*;
+
+    #[test]
+    fn check_streaming() {
+        let mut encoder = Encoder::new();
+        for index in 0..100u64 {
+            encoder.push(index * 10, (index % 7) as f64);
+        }
+        let bytes = encoder.finish();
+
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.next(), Some(Ok((0, 0.0))));
+        assert_eq!(decoder.nth(41), Some(Ok((420, 0.0))));
+        assert_eq!(decoder.count(), 57);
+    }
+
+    #[test]
+    fn check_truncated() {
+        let bytes = encode(&[(100, 1.5), (200, 2.5), (300, 7.25)]);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&[]).is_err());
+
+        // The error is yielded once
+        let mut decoder = Decoder::new(&bytes[..bytes.len() - 1]);
+        assert!(decoder.by_ref().any(|point| point.is_err()));
+        assert_eq!(decoder.next(), None);
+    }
+
+    #[test]
+    fn check_window_before_set() {
+        // Zero delta of delta, value in the previous window
+        assert_eq!(decode(&[0b01000000]), Err(DecodeError { bit_offset: 1 }));
+    }
+}
+
+// State of the previous point, shared by the encoder and the decoder
+#[derive(Debug, Default)]
+struct State {
+    timestamp: u64,
+    delta: u64,
+    // Bits of the value
+    value: u64,
+    // Leading and trailing zeros of the last written XOR
+    window: Option<(u32, u32)>,
+}
+
+fn push_delta_of_delta(buf: &mut BitBuf, delta_of_delta: i64) {
+    if delta_of_delta == 0 {
+        buf.push(false);
+        return;
+    }
+    let (prefix, prefix_width, width) = BUCKETS
+        .into_iter()
+        .find(|(_, _, width)| fits(delta_of_delta, *width))
+        .unwrap();
+    buf.push_bits(prefix, prefix_width);
+    buf.push_bits(delta_of_delta as u64, width);
+}
+
+// `None` at the end of the stream
+fn read_delta_of_delta(reader: &mut BitReader<'_>) -> Result<Option<i64>, DecodeError> {
+    // Number of ones before zero
+    let mut ones = 0;
+    while ones < END.1 && reader.read(1)? == 1 {
+        ones += 1;
+    }
+    if ones == 0 {
+        return Ok(Some(0));
+    }
+    if ones == END.1 {
+        return Ok(None);
+    }
+    let width = BUCKETS[ones - 1].2;
+    Ok(Some(sign_extend(reader.read(width)?, width)))
+}
+
+// Whether `value` is in the range of two's complement of `width` bits
+fn fits(value: i64, width: usize) -> bool {
+    sign_extend(value as u64, width) == value
+}
+
+fn sign_extend(value: u64, width: usize) -> i64 {
+    let shift = 64 - width as u32;
+    ((value << shift) as i64) >> shift
+}
@@ -0,0 +1,155 @@
+//! Codecs of integer and float columns built on the crate's bit routines
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Index;
+
+use crate::buf::BitBuf;
+use crate::read_bits;
+
+pub mod for_delta;
+pub mod gorilla;
+pub mod pfor;
+pub mod rle_hybrid;
+pub mod simple8b;
+
+/// Error of decoding data, that is truncated or was not produced
+/// by the encoder of the codec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// Offset of the bits that could not be decoded
+    pub bit_offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: encoded data is truncated or malformed at offset {}.",
+            self.bit_offset
+        )
+    }
+}
+
+impl Error for DecodeError {}
+
+// Sequential reader of bits of encoded data
+pub(crate) struct BitReader<'a> {
+    bytes: Bytes<'a>,
+    bit_offset: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], bit_offset: usize) -> Self {
+        Self {
+            bytes: Bytes(bytes),
+            bit_offset,
+        }
+    }
+
+    pub(crate) fn bit_offset(&self) -> usize {
+        self.bit_offset
+    }
+
+    // Number of bits after the current offset
+    pub(crate) fn remaining(&self) -> usize {
+        (self.bytes.0.len() * 8).saturating_sub(self.bit_offset)
+    }
+
+    // Reads `width` (up to 64) bits as a right aligned `u64`
+    pub(crate) fn read(&mut self, width: usize) -> Result<u64, DecodeError> {
+        self.check(width)?;
+        let value = read_bits(&self.bytes, self.bit_offset, width);
+        self.bit_offset += width;
+        Ok(value)
+    }
+
+    // Skips `bit_size` bits
+    pub(crate) fn skip(&mut self, bit_size: usize) -> Result<(), DecodeError> {
+        self.check(bit_size)?;
+        self.bit_offset += bit_size;
+        Ok(())
+    }
+
+    // Reads a value written by `push_with_width`
+    pub(crate) fn read_with_width(&mut self) -> Result<u64, DecodeError> {
+        let width = self.read_width()?;
+        self.read(width)
+    }
+
+    // Reads a 7 bit width of values (up to 64)
+    pub(crate) fn read_width(&mut self) -> Result<usize, DecodeError> {
+        let bit_offset = self.bit_offset;
+        match self.read(7)? as usize {
+            width @ 0..=64 => Ok(width),
+            _ => Err(DecodeError { bit_offset }),
+        }
+    }
+
+    fn check(&self, bit_size: usize) -> Result<(), DecodeError> {
+        if bit_size > self.remaining() {
+            return Err(DecodeError {
+                bit_offset: self.bit_offset,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Byte slice, that can be passed to the crate's bit routines
+struct Bytes<'a>(&'a [u8]);
+
+impl Index<usize> for Bytes<'_> {
+    type Output = u8;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+// Number of bits needed for `value`, zero for zero
+pub(crate) fn width_of(value: u64) -> usize {
+    (64 - value.leading_zeros()) as usize
+}
+
+// Appends the width of `value` (7 bits) and `value` in that width
+pub(crate) fn push_with_width(buf: &mut BitBuf, value: u64) {
+    let width = width_of(value);
+    buf.push_bits(width as u64, 7);
+    buf.push_bits(value, width);
+}
+
+#[cfg(test)]
+mod tests_bit_reader {
+    use super::*;
+
+    #[test]
+    fn check_read() {
+        let mut reader = BitReader::new(&[0b10110011, 0b10001111], 3);
+        assert_eq!(reader.read(5), Ok(0b10011));
+        assert_eq!(reader.read(0), Ok(0));
+        assert_eq!(reader.read(8), Ok(0b10001111));
+        assert_eq!(reader.read(1), Err(DecodeError { bit_offset: 16 }));
+    }
+
+    #[test]
+    fn check_with_width() {
+        let mut buf = BitBuf::new();
+        push_with_width(&mut buf, 0);
+        push_with_width(&mut buf, 1000);
+        push_with_width(&mut buf, u64::MAX);
+        buf.push_bits(127, 7);
+
+        let bytes = buf.into_vec(Default::default());
+        let mut reader = BitReader::new(&bytes, 0);
+        assert_eq!(reader.read_with_width(), Ok(0));
+        assert_eq!(reader.read_with_width(), Ok(1000));
+        assert_eq!(reader.read_with_width(), Ok(u64::MAX));
+        assert_eq!(
+            reader.read_width(),
+            Err(DecodeError {
+                bit_offset: 7 + 7 + 10 + 7 + 64
+            })
+        );
+    }
+}
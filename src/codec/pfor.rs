@@ -0,0 +1,237 @@
+//! Patched frame-of-reference (PFor) bit-packing of integer columns
+//!
+//! Values are split into blocks of [`BLOCK_LEN`]. Every block packs the low
+//! bits of all values in a width, that is picked for the smallest size of
+//! the block. The values, that do not fit the width, are exceptions, their
+//! positions and high bits are stored after the packed values
+//!
+//! ```text
+//! column:    | len width (7) | len | block | block | ...
+//! block:     | width (7) | exception count (8) | high width (7) |
+//!            | low bits (len * width) | positions (count * 7) |
+//!            | high bits (count * high width) |
+//! ```
+//!
+//! [`encode_delta`] (PForDelta) packs differences of neighbouring values,
+//! that are small for sorted columns like posting lists
+
+use super::{push_with_width, width_of, BitReader, DecodeError};
+use crate::buf::{BitBuf, Padding};
+
+/// Number of values in a block, the last block can be shorter
+pub const BLOCK_LEN: usize = 128;
+
+// Width of a position of an exception in a block
+const POSITION_WIDTH: usize = 7;
+
+/// Encodes `values` to bytes
+pub fn encode(values: &[u64]) -> Vec<u8> {
+    let mut buf = BitBuf::new();
+    push_with_width(&mut buf, values.len() as u64);
+    for block in values.chunks(BLOCK_LEN) {
+        encode_block(&mut buf, block);
+    }
+    buf.into_vec(Padding::Zeros)
+}
+
+/// Decodes all values of bytes produced by [`encode`]
+pub fn decode(bytes: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    let mut reader = BitReader::new(bytes, 0);
+    let len = reader.read_with_width()? as usize;
+
+    // Every block takes at least its header
+    let block_count = len.div_ceil(BLOCK_LEN);
+    if block_count > reader.remaining() / (7 + 8 + 7) {
+        return Err(DecodeError {
+            bit_offset: reader.bit_offset(),
+        });
+    }
+    let mut values = Vec::with_capacity(len);
+    for index in 0..block_count {
+        let block_len = (len - index * BLOCK_LEN).min(BLOCK_LEN);
+        decode_block(&mut reader, block_len, &mut values)?;
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests_encode {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let values: Vec<u64> = (0..1000u64)
+            .map(|index| match index % 37 {
+                0 => index * 1_000_003,
+                5 => u64::MAX - index,
+                _ => index % 13,
+            })
+            .collect();
+        assert_eq!(decode(&encode(&values)), Ok(values));
+
+        assert_eq!(decode(&encode(&[])), Ok(vec![]));
+        assert_eq!(decode(&encode(&[0; 130])), Ok(vec![0; 130]));
+    }
+
+    #[test]
+    fn check_exceptions_size() {
+        // 126 values of 3 bits and 2 outliers of 40 bits
+        let mut values: Vec<u64> = (0..128u64).map(|index| index % 8).collect();
+        values[10] = 1 << 39;
+        values[100] = (1 << 39) + 5;
+        let bytes = encode(&values);
+
+        // len, header, low bits, positions, high bits of 37 bits
+        let bit_len = (7 + 8) + (7 + 8 + 7) + 128 * 3 + 2 * 7 + 2 * 37;
+        assert_eq!(bytes.len(), (bit_len as usize).div_ceil(8));
+        assert_eq!(decode(&bytes), Ok(values));
+    }
+
+    #[test]
+    fn check_malformed() {
+        let values: Vec<u64> = (0..200u64).map(|index| index * index).collect();
+        let bytes = encode(&values);
+        assert!(decode(&bytes[..bytes.len() - 2]).is_err());
+        assert!(decode(&[]).is_err());
+
+        // Position of an exception out of the block of one value
+        let mut buf = BitBuf::new();
+        push_with_width(&mut buf, 1);
+        buf.push_bits(0, 7);
+        buf.push_bits(1, 8);
+        buf.push_bits(1, 7);
+        buf.push_bits(5, 7);
+        buf.push_bits(1, 1);
+        assert_eq!(
+            decode(&buf.into_vec(Padding::Zeros)),
+            Err(DecodeError { bit_offset: 8 + 22 })
+        );
+    }
+}
+
+/// Encodes differences of neighbouring `values`, the first value is
+/// the difference from zero. Differences are computed with wrapping
+/// arithmetic, so decreasing values take the full width
+pub fn encode_delta(values: &[u64]) -> Vec<u8> {
+    let mut previous = 0u64;
+    let deltas: Vec<u64> = values
+        .iter()
+        .map(|value| {
+            let delta = value.wrapping_sub(previous);
+            previous = *value;
+            delta
+        })
+        .collect();
+    encode(&deltas)
+}
+
+/// Decodes all values of bytes produced by [`encode_delta`]
+pub fn decode_delta(bytes: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    let mut values = decode(bytes)?;
+    let mut previous = 0u64;
+    for value in values.iter_mut() {
+        previous = previous.wrapping_add(*value);
+        *value = previous;
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests_delta {
+    use super::*;
+
+    #[test]
+    fn check_delta() {
+        // Sorted identifiers of a posting list
+        let values: Vec<u64> = (0..500u64)
+            .map(|index| 1_000_000 + index * 17 + index % 3 + (index / 100) * 5000)
+            .collect();
+        let bytes = encode_delta(&values);
+        assert!(bytes.len() < encode(&values).len() / 3);
+        assert_eq!(decode_delta(&bytes), Ok(values));
+
+        let decreasing: Vec<u64> = (0..300u64).map(|index| 1000 - index * 3).collect();
+        assert_eq!(decode_delta(&encode_delta(&decreasing)), Ok(decreasing));
+    }
+}
+
+fn encode_block(buf: &mut BitBuf, block: &[u64]) {
+    // Width with the smallest size of the low bits and the exceptions
+    let (width, _) = (0..=64)
+        .map(|width| {
+            let highs = block.iter().map(|value| high_bits(*value, width));
+            let count = highs.clone().filter(|high| *high != 0).count();
+            let high_width = width_of(highs.max().unwrap_or_default());
+            let bit_len = block.len() * width + count * (POSITION_WIDTH + high_width);
+            (width, bit_len)
+        })
+        .min_by_key(|(_, bit_len)| *bit_len)
+        .unwrap();
+
+    let exceptions: Vec<(usize, u64)> = block
+        .iter()
+        .map(|value| high_bits(*value, width))
+        .enumerate()
+        .filter(|(_, high)| *high != 0)
+        .collect();
+    let high_width = width_of(
+        exceptions
+            .iter()
+            .map(|(_, high)| *high)
+            .max()
+            .unwrap_or_default(),
+    );
+
+    buf.push_bits(width as u64, 7);
+    buf.push_bits(exceptions.len() as u64, 8);
+    buf.push_bits(high_width as u64, 7);
+    for value in block {
+        buf.push_bits(*value, width);
+    }
+    for (position, _) in &exceptions {
+        buf.push_bits(*position as u64, POSITION_WIDTH);
+    }
+    for (_, high) in &exceptions {
+        buf.push_bits(*high, high_width);
+    }
+}
+
+// Decodes a block of `len` values and appends them to `values`
+fn decode_block(
+    reader: &mut BitReader<'_>,
+    len: usize,
+    values: &mut Vec<u64>,
+) -> Result<(), DecodeError> {
+    let width = reader.read_width()?;
+    let count = reader.read(8)? as usize;
+    let high_width = reader.read_width()?;
+    if count > len || width + high_width > 64 {
+        return Err(DecodeError {
+            bit_offset: reader.bit_offset() - (7 + 8 + 7),
+        });
+    }
+
+    let start = values.len();
+    for _ in 0..len {
+        values.push(reader.read(width)?);
+    }
+    let mut positions = Vec::with_capacity(count);
+    for _ in 0..count {
+        let bit_offset = reader.bit_offset();
+        let position = reader.read(POSITION_WIDTH)? as usize;
+        if position >= len {
+            return Err(DecodeError { bit_offset });
+        }
+        positions.push(position);
+    }
+    for position in positions {
+        let high = reader.read(high_width)?;
+        values[start + position] |= high.checked_shl(width as u32).unwrap_or_default();
+    }
+    Ok(())
+}
+
+// Bits of `value` out of `width`
+fn high_bits(value: u64, width: usize) -> u64 {
+    value.checked_shr(width as u32).unwrap_or_default()
+}
@@ -0,0 +1,269 @@
+//! RLE and bit-packing hybrid encoding of Parquet (definition and
+//! repetition levels, dictionary indices)
+//!
+//! ```text
+//! data:       run*
+//! run:        bit-packed run | RLE run
+//! bit-packed: varint(groups << 1 | 1) | groups * bit_width bytes
+//! RLE:        varint(len << 1) | value in bit_width.div_ceil(8) bytes (LE)
+//! ```
+//!
+//! Values of a bit-packed run are packed by groups of 8 from the least
+//! significant bit of a byte. The crate's routines write from the most
+//! significant bit, so the values are written with the reflected order
+//! of bits and then every byte is reflected
+//!
+//! **NOTE**: The length prefix of data, that some Parquet pages have,
+//! is not a part of the encoding
+
+use super::DecodeError;
+use crate::buf::{BitBuf, Padding};
+use crate::read_bits;
+use crate::reverse::{reflect_bits, REVERSED};
+
+// Number of values in a group of a bit-packed run
+const GROUP_LEN: usize = 8;
+
+/// Encodes `values` of `bit_width` bits (up to 32). Runs of at least
+/// 8 equal values are encoded as RLE runs, other values are bit-packed,
+/// the last group is padded with zeros
+///
+/// **PANIC**: If `bit_width` large than 32
+///
+/// **NOTE**: Bits of the values that are out of `bit_width` are discarded
+pub fn encode(values: &[u32], bit_width: usize) -> Vec<u8> {
+    assert!(bit_width <= 32, "bit_width large than 32");
+    let values: Vec<u32> = values
+        .iter()
+        .map(|value| value & max_value(bit_width))
+        .collect();
+
+    let mut bytes = vec![];
+    let mut start = 0;
+    while start < values.len() {
+        let repeated = repeated_len(&values[start..]);
+        if repeated >= GROUP_LEN {
+            push_rle_run(&mut bytes, values[start], repeated, bit_width);
+            start += repeated;
+            continue;
+        }
+
+        // Bit-pack groups until a group starts a long enough run
+        let mut end = start + GROUP_LEN;
+        while end < values.len() && repeated_len(&values[end..]) < GROUP_LEN {
+            end += GROUP_LEN;
+        }
+        let end = end.min(values.len());
+        push_bit_packed_run(&mut bytes, &values[start..end], bit_width);
+        start = end;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests_encode {
+    use super::*;
+
+    #[test]
+    fn check_spec_bit_packed() {
+        // Example of the Parquet format specification
+        let values = [0, 1, 2, 3, 4, 5, 6, 7];
+        let bytes = [0x03, 0b10001000, 0b11000110, 0b11111010];
+        assert_eq!(encode(&values, 3), bytes);
+        assert_eq!(decode(&bytes, 3, 8), Ok(values.to_vec()));
+    }
+
+    #[test]
+    fn check_rle_run() {
+        // 100 values `4`, the header 200 takes two bytes of varint
+        let values = [4; 100];
+        let bytes = [0xC8, 0x01, 0x04];
+        assert_eq!(encode(&values, 3), bytes);
+        assert_eq!(decode(&bytes, 3, 100), Ok(values.to_vec()));
+
+        // The value takes two bytes in little-endian for 9..=16 bits
+        assert_eq!(encode(&[0x1234; 8], 13), [0x10, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn check_mixed() {
+        // Definition levels: 10 nulls, 3 values, then 16 values
+        let mut values = vec![0u32; 10];
+        values.extend([1, 0, 1]);
+        values.extend([1; 16]);
+        let bytes = encode(&values, 1);
+
+        // 10 zeros
+        assert_eq!(bytes[..2], [0x14, 0x00]);
+        // One group of 1, 0, 1, 1, 1, 1, 1, 1 (from the least significant bit)
+        assert_eq!(bytes[2..4], [0x03, 0b11111101]);
+        // 11 ones
+        assert_eq!(bytes[4..], [0x16, 0x01]);
+        assert_eq!(decode(&bytes, 1, values.len()), Ok(values));
+    }
+
+    #[test]
+    fn check_padding() {
+        let values: Vec<u32> = (0..13).map(|index| index * 5 % 32).collect();
+        let bytes = encode(&values, 5);
+
+        // Two groups of 5 bytes, 3 values of padding
+        assert_eq!(bytes.len(), 1 + 2 * 5);
+        assert_eq!(decode(&bytes, 5, 13), Ok(values));
+    }
+}
+
+/// Decodes `len` values of `bit_width` bits (up to 32) of bytes produced by
+/// [`encode`] or by another Parquet writer. Values of padding of the last
+/// bit-packed group are skipped
+///
+/// **PANIC**: If `bit_width` large than 32
+pub fn decode(bytes: &[u8], bit_width: usize, len: usize) -> Result<Vec<u32>, DecodeError> {
+    assert!(bit_width <= 32, "bit_width large than 32");
+
+    let mut values = Vec::with_capacity(len.min(bytes.len() * 8));
+    let mut position = 0;
+    while values.len() < len {
+        let header_position = position;
+        let header = read_varint(bytes, &mut position)?;
+        let error = DecodeError {
+            bit_offset: header_position * 8,
+        };
+
+        if header & 1 == 0 {
+            let run_len = usize::try_from(header >> 1).map_err(|_| error.clone())?;
+            let value_len = bit_width.div_ceil(8);
+            let value_bytes = bytes
+                .get(position..position + value_len)
+                .ok_or(error.clone())?;
+            position += value_len;
+
+            let value = value_bytes
+                .iter()
+                .rev()
+                .fold(0u32, |value, byte| (value << 8) | *byte as u32);
+            if value > max_value(bit_width) || run_len == 0 {
+                return Err(error);
+            }
+            let run_len = run_len.min(len - values.len());
+            values.extend(std::iter::repeat_n(value, run_len));
+        } else {
+            let groups = usize::try_from(header >> 1).map_err(|_| error.clone())?;
+            let run_bytes_len = groups.checked_mul(bit_width).ok_or(error.clone())?;
+            let run_bytes = bytes
+                .get(position..position.saturating_add(run_bytes_len))
+                .ok_or(error.clone())?;
+            position += run_bytes_len;
+
+            // Back to the order of the crate's routines
+            let reflected: Vec<u8> = run_bytes
+                .iter()
+                .map(|byte| REVERSED[*byte as usize])
+                .collect();
+            let run_len = (groups * GROUP_LEN).min(len - values.len());
+            for index in 0..run_len {
+                let value = read_bits(&reflected, index * bit_width, bit_width);
+                values.push(reflect_bits(value, bit_width) as u32);
+            }
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests_decode {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let values: Vec<u32> = (0..5000u32)
+            .map(|index| match index / 100 % 3 {
+                0 => index * 7919 % 1024,
+                1 => 77,
+                _ => index % 3,
+            })
+            .collect();
+        for bit_width in [10, 17, 32] {
+            let bytes = encode(&values, bit_width);
+            assert_eq!(decode(&bytes, bit_width, values.len()), Ok(values.clone()));
+        }
+    }
+
+    #[test]
+    fn check_zero_width() {
+        let bytes = encode(&[0; 20], 0);
+        assert_eq!(bytes, [40]);
+        assert_eq!(decode(&bytes, 0, 20), Ok(vec![0; 20]));
+    }
+
+    #[test]
+    fn check_malformed() {
+        // Truncated bit-packed run
+        assert!(decode(&[0x03, 0x88], 3, 8).is_err());
+        // Value of RLE run out of the width
+        assert!(decode(&[0x10, 0x08], 3, 8).is_err());
+        // Not enough runs
+        assert!(decode(&[0x10, 0x01], 3, 9).is_err());
+        // Varint without the end
+        assert!(decode(&[0x80, 0x80], 3, 1).is_err());
+    }
+}
+
+fn push_rle_run(bytes: &mut Vec<u8>, value: u32, len: usize, bit_width: usize) {
+    push_varint(bytes, (len as u64) << 1);
+    bytes.extend_from_slice(&value.to_le_bytes()[..bit_width.div_ceil(8)]);
+}
+
+fn push_bit_packed_run(bytes: &mut Vec<u8>, values: &[u32], bit_width: usize) {
+    let groups = values.len().div_ceil(GROUP_LEN);
+    push_varint(bytes, ((groups as u64) << 1) | 1);
+
+    let mut buf = BitBuf::with_capacity(groups * GROUP_LEN * bit_width);
+    for value in values {
+        buf.push_bits(reflect_bits(*value as u64, bit_width), bit_width);
+    }
+    buf.push_bits(0, (groups * GROUP_LEN - values.len()) * bit_width);
+
+    let packed = buf.into_vec(Padding::Zeros);
+    bytes.extend(packed.iter().map(|byte| REVERSED[*byte as usize]));
+}
+
+// Number of values at the start that are equal to the first one
+fn repeated_len(values: &[u32]) -> usize {
+    match values.first() {
+        Some(first) => values.iter().take_while(|value| *value == first).count(),
+        None => 0,
+    }
+}
+
+fn max_value(bit_width: usize) -> u32 {
+    crate::int::max_hold::<u32>(bit_width as u32)
+}
+
+// Appends ULEB128 varint
+fn push_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0b10000000 {
+        bytes.push((value as u8) | 0b10000000);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// Reads ULEB128 varint at `position` and moves it after the varint
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, DecodeError> {
+    let error = DecodeError {
+        bit_offset: *position * 8,
+    };
+
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position).ok_or(error.clone())?;
+        *position += 1;
+
+        value |= ((byte & 0b01111111) as u64) << shift;
+        if byte & 0b10000000 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(error)
+}
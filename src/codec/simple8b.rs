@@ -0,0 +1,155 @@
+//! Simple-8b packing of small integers into 64 bit words
+//!
+//! Every word starts with a 4 bit selector, that sets the number of values
+//! in the word and their width. Words are stored in big-endian
+//!
+//! ```text
+//! word:     | selector (4) | values (up to 60) |
+//!
+//! selector: 0   1   2  3  4  5  6  7  8  9  10 11 12 13 14 15
+//! values:   240 120 60 30 20 15 12 10 8  7  6  5  4  3  2  1
+//! width:    0   0   1  2  3  4  5  6  7  8  10 12 15 20 30 60
+//! ```
+//!
+//! The encoder picks the densest selector for the next values, the last
+//! words are not padded, so all values of decoded words are the values
+//! of the column
+
+use super::DecodeError;
+use crate::{bit_write, read_bits};
+
+/// The largest value, that can be packed
+pub const MAX_VALUE: u64 = (1 << 60) - 1;
+
+// Number of values and their width for every selector
+const SELECTORS: [(usize, usize); 16] = [
+    (240, 0),
+    (120, 0),
+    (60, 1),
+    (30, 2),
+    (20, 3),
+    (15, 4),
+    (12, 5),
+    (10, 6),
+    (8, 7),
+    (7, 8),
+    (6, 10),
+    (5, 12),
+    (4, 15),
+    (3, 20),
+    (2, 30),
+    (1, 60),
+];
+
+/// Encodes `values` to words
+///
+/// **PANIC**: If a value large than [`MAX_VALUE`]
+pub fn encode(values: &[u64]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut start = 0;
+    while start < values.len() {
+        let rest = &values[start..];
+        let (selector, (len, width)) = SELECTORS
+            .into_iter()
+            .enumerate()
+            .find(|(_, (len, width))| {
+                *len <= rest.len() && rest[..*len].iter().all(|value| fits(*value, *width))
+            })
+            .expect("value large than MAX_VALUE");
+
+        let mut word = [0u8; 8];
+        bit_write(&mut word, 0, 4, &(selector as u8).to_be_bytes(), 1);
+        for (index, value) in rest[..len].iter().enumerate() {
+            bit_write(&mut word, 4 + index * width, width, &value.to_be_bytes(), 8);
+        }
+        bytes.extend_from_slice(&word);
+        start += len;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests_encode {
+    use super::*;
+
+    #[test]
+    fn check_selectors() {
+        // 240 zeros
+        assert_eq!(encode(&[0; 240]), [0, 0, 0, 0, 0, 0, 0, 0]);
+        // 3 values of 20 bits
+        assert_eq!(
+            encode(&[1, 2, 3]),
+            [0xD0, 0x00, 0x01, 0x00, 0x00, 0x20, 0x00, 0x03]
+        );
+        // One value of 60 bits
+        assert_eq!(
+            encode(&[MAX_VALUE]),
+            [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn check_densest() {
+        // 60 values of one bit, then 7 values of 8 bits
+        let mut values: Vec<u64> = (0..60).map(|index| index % 2).collect();
+        values.extend([200; 7]);
+        let bytes = encode(&values);
+        assert_eq!(bytes.len(), 2 * 8);
+        assert_eq!(bytes[0] >> 4, 2);
+        assert_eq!(bytes[8] >> 4, 9);
+        assert_eq!(decode(&bytes), Ok(values));
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_too_large() {
+        encode(&[MAX_VALUE + 1]);
+    }
+}
+
+/// Decodes all values of words produced by [`encode`]
+pub fn decode(bytes: &[u8]) -> Result<Vec<u64>, DecodeError> {
+    if !bytes.len().is_multiple_of(8) {
+        return Err(DecodeError {
+            bit_offset: bytes.len() / 8 * 64,
+        });
+    }
+
+    let mut values = vec![];
+    for word in bytes.chunks_exact(8) {
+        let word: [u8; 8] = word.try_into().unwrap();
+        let (len, width) = SELECTORS[read_bits(&word, 0, 4) as usize];
+        values.extend((0..len).map(|index| read_bits(&word, 4 + index * width, width)));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests_decode {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let values: Vec<u64> = (0..3000u64)
+            .map(|index| match index / 300 % 4 {
+                0 => 0,
+                1 => index % 5,
+                2 => index * 7919 % 100_000,
+                _ => index << 40,
+            })
+            .collect();
+        assert_eq!(decode(&encode(&values)), Ok(values));
+        assert_eq!(decode(&encode(&[])), Ok(vec![]));
+    }
+
+    #[test]
+    fn check_truncated() {
+        let bytes = encode(&[1, 2, 3]);
+        assert_eq!(decode(&bytes[..7]), Err(DecodeError { bit_offset: 0 }));
+    }
+}
+
+// Whether `value` takes at most `width` bits
+fn fits(value: u64, width: usize) -> bool {
+    value.checked_shr(width as u32).unwrap_or_default() == 0
+}
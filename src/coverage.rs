@@ -0,0 +1,218 @@
+use std::ops::{Index, IndexMut, Range};
+
+use crate::{bit_read, membitcpy};
+
+/// Wrapper around a decoding source, that records every bit range
+/// read through it. After decoding it reports which bits were never
+/// consumed and which were consumed more than once
+///
+/// **NOTE**: Reads done directly on the source, past the wrapper,
+/// are not visible to it (see [`Coverage::mark_read`])
+pub struct Coverage<'s, S> {
+    source: &'s S,
+    bit_len: usize,
+    // Number of reads of every bit of the source, saturated at `u8::MAX`
+    reads: Vec<u8>,
+}
+
+impl<'s, S> Coverage<'s, S>
+where
+    S: Index<usize, Output = u8>,
+{
+    /// Starts tracking of `source`, that is `byte_source_len` bytes long
+    pub fn new(source: &'s S, byte_source_len: usize) -> Self {
+        Self {
+            source,
+            bit_len: byte_source_len * 8,
+            reads: vec![0; byte_source_len * 8],
+        }
+    }
+
+    /// The tracked source
+    pub fn source(&self) -> &'s S {
+        self.source
+    }
+
+    /// Length of the tracked source in bits
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Same as [`bit_read`], but with the tracked source
+    pub fn bit_read<T>(
+        &mut self,
+        source_bit_offset: usize,
+        recordable_bit_size: usize,
+        target: &mut T,
+        byte_target_len: usize,
+    ) where
+        T: IndexMut<usize, Output = u8> + ?Sized,
+    {
+        self.mark_read(source_bit_offset, recordable_bit_size);
+        bit_read(
+            self.source,
+            source_bit_offset,
+            recordable_bit_size,
+            target,
+            byte_target_len,
+        );
+    }
+
+    /// Same as [`membitcpy`], but with the tracked source
+    pub fn membitcpy<T>(
+        &mut self,
+        target: &mut T,
+        target_bit_offset: usize,
+        recordable_bit_size: usize,
+        source_bit_offset: usize,
+    ) where
+        T: IndexMut<usize, Output = u8>,
+    {
+        self.mark_read(source_bit_offset, recordable_bit_size);
+        membitcpy(
+            target,
+            target_bit_offset,
+            recordable_bit_size,
+            self.source,
+            source_bit_offset,
+        );
+    }
+
+    /// Records the read of `bit_size` bits at `bit_offset`, that was
+    /// performed without the wrapper
+    ///
+    /// **PANIC**: If the range is out of the source
+    pub fn mark_read(&mut self, bit_offset: usize, bit_size: usize) {
+        assert!(
+            bit_offset + bit_size <= self.bit_len,
+            "read range out of source bit size"
+        );
+
+        for count in &mut self.reads[bit_offset..bit_offset + bit_size] {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    /// How many times the bit at `bit_index` was read
+    /// (saturated at `u8::MAX`)
+    pub fn read_count(&self, bit_index: usize) -> u8 {
+        self.reads[bit_index]
+    }
+
+    /// Whether every bit of the source was read exactly once
+    pub fn is_exact(&self) -> bool {
+        self.reads.iter().all(|count| *count == 1)
+    }
+
+    /// Bitmap with the same layout as the source, where
+    /// set bits were never read
+    pub fn uncovered_bitmap(&self) -> Vec<u8> {
+        self.bitmap(|count| count == 0)
+    }
+
+    /// Bitmap with the same layout as the source, where
+    /// set bits were read more than once
+    pub fn multiply_read_bitmap(&self) -> Vec<u8> {
+        self.bitmap(|count| count > 1)
+    }
+
+    /// Ranges of bits that were never read, in ascending order
+    pub fn uncovered_ranges(&self) -> Vec<Range<usize>> {
+        self.ranges(|count| count == 0)
+    }
+
+    /// Ranges of bits that were read more than once, in ascending order
+    pub fn multiply_read_ranges(&self) -> Vec<Range<usize>> {
+        self.ranges(|count| count > 1)
+    }
+
+    fn bitmap(&self, predicate: impl Fn(u8) -> bool) -> Vec<u8> {
+        let mut bitmap = vec![0u8; self.bit_len / 8];
+        for (index, count) in self.reads.iter().enumerate() {
+            if predicate(*count) {
+                bitmap[index / 8] |= 0b10000000 >> (index % 8);
+            }
+        }
+        bitmap
+    }
+
+    fn ranges(&self, predicate: impl Fn(u8) -> bool) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        let mut start = None;
+        for (index, count) in self.reads.iter().enumerate() {
+            match (predicate(*count), start) {
+                (true, None) => start = Some(index),
+                (false, Some(begin)) => {
+                    ranges.push(begin..index);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(begin) = start {
+            ranges.push(begin..self.bit_len);
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests_coverage {
+    use super::*;
+
+    #[test]
+    fn check_uncovered() {
+        let source = [0b10110000u8, 0b00001111u8];
+        let mut coverage = Coverage::new(&source, source.len());
+
+        let mut target = [0u8; 1];
+        let target_len = target.len();
+        coverage.bit_read(0, 4, &mut target, target_len);
+        assert_eq!(target, [0b00001011]);
+
+        let mut target = [0u8; 1];
+        coverage.bit_read(12, 4, &mut target, target_len);
+        assert_eq!(target, [0b00001111]);
+
+        assert!(!coverage.is_exact());
+        assert_eq!(coverage.uncovered_ranges(), vec![4..12]);
+        assert_eq!(coverage.uncovered_bitmap(), vec![0b00001111, 0b11110000]);
+        assert!(coverage.multiply_read_ranges().is_empty());
+    }
+
+    #[test]
+    fn check_multiply_read() {
+        let source = [0b11111111u8, 0b11111111u8];
+        let mut coverage = Coverage::new(&source, source.len());
+
+        let mut target = [0u8; 2];
+        coverage.membitcpy(&mut target, 0, 10, 0);
+        coverage.membitcpy(&mut target, 0, 8, 6);
+        coverage.mark_read(14, 2);
+
+        assert_eq!(coverage.read_count(7), 2);
+        assert_eq!(coverage.multiply_read_ranges(), vec![6..10]);
+        assert_eq!(
+            coverage.multiply_read_bitmap(),
+            vec![0b00000011, 0b11000000]
+        );
+        assert!(coverage.uncovered_ranges().is_empty());
+    }
+
+    #[test]
+    fn check_exact() {
+        let source = [0u8; 3];
+        let mut coverage = Coverage::new(&source, source.len());
+        coverage.mark_read(0, 5);
+        coverage.mark_read(5, 19);
+        assert!(coverage.is_exact());
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_out_of_range() {
+        let source = [0u8; 1];
+        let mut coverage = Coverage::new(&source, source.len());
+        coverage.mark_read(4, 5);
+    }
+}
@@ -0,0 +1,358 @@
+//! Fixed-point numbers in Q-format of any width up to 64 bits
+//!
+//! `Qm.n` has `m` integer bits and `n` fractional bits, a signed number
+//! has one more bit for the sign. The stored integer (raw value) is the
+//! number multiplied by `2^n`, signed numbers are in two's complement
+//!
+//! ```text
+//! signed Q3.12:   | s | iii | ffffffffffff |  16 bits, -8.0..=7.99975586
+//! unsigned Q0.10: | ffffffffff |              10 bits, 0.0..=0.99902344
+//! ```
+
+use std::ops::{Index, IndexMut};
+
+use crate::int::{is_value_in_range, max_hold, min_hold};
+use crate::policy::OutOfRangeError;
+use crate::{bit_write, read_bits};
+
+/// Layout of a Q-format field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QFormat {
+    signed: bool,
+    int_bits: u32,
+    frac_bits: u32,
+}
+
+impl QFormat {
+    /// Signed `Qm.n` of `1 + int_bits + frac_bits` bits
+    ///
+    /// **PANIC**: If the width large than 64
+    pub fn signed(int_bits: u32, frac_bits: u32) -> Self {
+        Self::new(true, int_bits, frac_bits)
+    }
+
+    /// Unsigned `UQm.n` of `int_bits + frac_bits` bits
+    ///
+    /// **PANIC**: If the width large than 64
+    pub fn unsigned(int_bits: u32, frac_bits: u32) -> Self {
+        Self::new(false, int_bits, frac_bits)
+    }
+
+    fn new(signed: bool, int_bits: u32, frac_bits: u32) -> Self {
+        let format = Self {
+            signed,
+            int_bits,
+            frac_bits,
+        };
+        assert!(format.bit_size() <= 64, "Q-format large than 64 bits");
+        format
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    pub fn int_bits(&self) -> u32 {
+        self.int_bits
+    }
+
+    pub fn frac_bits(&self) -> u32 {
+        self.frac_bits
+    }
+
+    /// Width of the field, including the sign bit
+    pub fn bit_size(&self) -> usize {
+        (self.signed as u32 + self.int_bits + self.frac_bits) as usize
+    }
+
+    /// Difference of neighbouring numbers, `2^-n`
+    pub fn resolution(&self) -> f64 {
+        (-(self.frac_bits as f64)).exp2()
+    }
+
+    /// The smallest raw value
+    pub fn min_raw(&self) -> i128 {
+        match self.signed {
+            true => min_hold::<i128>(self.bit_size() as u32),
+            false => 0,
+        }
+    }
+
+    /// The largest raw value
+    pub fn max_raw(&self) -> i128 {
+        match self.signed {
+            true => max_hold::<i128>(self.bit_size() as u32),
+            false => max_hold::<i128>(self.bit_size() as u32 + 1),
+        }
+    }
+
+    /// Whether `raw` can be stored in the field
+    pub fn is_raw_in_range(&self, raw: i128) -> bool {
+        match self.signed {
+            true => is_value_in_range(self.bit_size() as u32, raw),
+            false => raw >= 0 && is_value_in_range(self.bit_size() as u32 + 1, raw),
+        }
+    }
+
+    /// Reads a field from source by bit offset
+    ///
+    /// **PANIC**: If the source is too small
+    pub fn read<S>(&self, source: &S, source_bit_offset: usize) -> Fixed
+    where
+        S: Index<usize, Output = u8>,
+    {
+        let bits = read_bits(source, source_bit_offset, self.bit_size());
+        let raw = match self.signed {
+            // Spreads the sign bit to the left
+            true => {
+                let shift = 64 - self.bit_size() as u32;
+                (((bits << shift) as i64) >> shift) as i128
+            }
+            false => bits as i128,
+        };
+        Fixed { raw, format: *self }
+    }
+
+    /// Reads a field as `f64`, see [`Fixed::to_f64`]
+    ///
+    /// **PANIC**: If the source is too small
+    pub fn read_f64<S>(&self, source: &S, source_bit_offset: usize) -> f64
+    where
+        S: Index<usize, Output = u8>,
+    {
+        self.read(source, source_bit_offset).to_f64()
+    }
+
+    /// Reads a field as `f32`, see [`Fixed::to_f32`]
+    ///
+    /// **PANIC**: If the source is too small
+    pub fn read_f32<S>(&self, source: &S, source_bit_offset: usize) -> f32
+    where
+        S: Index<usize, Output = u8>,
+    {
+        self.read(source, source_bit_offset).to_f32()
+    }
+
+    /// Writes `value` rounded to the nearest number of the format
+    /// to target by bit offset. A value out of the range of the format
+    /// or NaN is rejected, the target is not touched
+    ///
+    /// **PANIC**: If the target is too small
+    ///
+    /// **NOTE**: The raw value is ORed into the target as by [`bit_write`],
+    /// so the field is expected to be clean
+    pub fn write_f64<T>(
+        &self,
+        target: &mut T,
+        target_bit_offset: usize,
+        value: f64,
+    ) -> Result<(), OutOfRangeError>
+    where
+        T: IndexMut<usize, Output = u8>,
+    {
+        match Fixed::from_f64(*self, value) {
+            Some(fixed) => {
+                fixed.write(target, target_bit_offset);
+                Ok(())
+            }
+            None => Err(OutOfRangeError {
+                bit_offset: target_bit_offset,
+                bit_size: self.bit_size(),
+            }),
+        }
+    }
+
+    /// Same as [`write_f64`](Self::write_f64) for `f32`
+    pub fn write_f32<T>(
+        &self,
+        target: &mut T,
+        target_bit_offset: usize,
+        value: f32,
+    ) -> Result<(), OutOfRangeError>
+    where
+        T: IndexMut<usize, Output = u8>,
+    {
+        self.write_f64(target, target_bit_offset, value as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests_q_format {
+    use super::*;
+
+    #[test]
+    fn check_ranges() {
+        let q3_12 = QFormat::signed(3, 12);
+        assert_eq!(q3_12.bit_size(), 16);
+        assert_eq!(q3_12.min_raw(), -32768);
+        assert_eq!(q3_12.max_raw(), 32767);
+        assert_eq!(q3_12.resolution(), 1.0 / 4096.0);
+
+        let uq0_10 = QFormat::unsigned(0, 10);
+        assert_eq!(uq0_10.bit_size(), 10);
+        assert_eq!(uq0_10.min_raw(), 0);
+        assert_eq!(uq0_10.max_raw(), 1023);
+        assert!(!uq0_10.is_raw_in_range(-1));
+        assert!(!uq0_10.is_raw_in_range(1024));
+
+        let uq64 = QFormat::unsigned(32, 32);
+        assert_eq!(uq64.max_raw(), u64::MAX as i128);
+    }
+
+    #[test]
+    fn check_write_read() {
+        let q3_12 = QFormat::signed(3, 12);
+        let mut target = vec![0u8; 4];
+        q3_12.write_f64(&mut target, 0, 1.5).unwrap();
+        q3_12.write_f32(&mut target, 16, -1.0).unwrap();
+        assert_eq!(target, [0x18, 0x00, 0xF0, 0x00]);
+        assert_eq!(q3_12.read_f64(&target, 0), 1.5);
+        assert_eq!(q3_12.read_f32(&target, 16), -1.0);
+
+        // Unsigned Q0.10 after 3 bits
+        let uq0_10 = QFormat::unsigned(0, 10);
+        let mut target = vec![0u8; 2];
+        uq0_10.write_f64(&mut target, 3, 0.5).unwrap();
+        assert_eq!(target, [0b00010000, 0b00000000]);
+        assert_eq!(uq0_10.read_f64(&target, 3), 0.5);
+    }
+
+    #[test]
+    fn check_overflow() {
+        let q3_12 = QFormat::signed(3, 12);
+        let mut target = vec![0u8; 2];
+        let error = OutOfRangeError {
+            bit_offset: 0,
+            bit_size: 16,
+        };
+        assert_eq!(q3_12.write_f64(&mut target, 0, 8.0), Err(error.clone()));
+        assert_eq!(q3_12.write_f64(&mut target, 0, f64::NAN), Err(error));
+        assert_eq!(target, [0, 0]);
+
+        // The ends of the range
+        q3_12.write_f64(&mut target, 0, -8.0).unwrap();
+        assert_eq!(q3_12.read_f64(&target, 0), -8.0);
+        assert!(QFormat::unsigned(0, 10)
+            .write_f64(&mut vec![0u8; 2], 0, -0.001)
+            .is_err());
+    }
+
+    #[test]
+    fn check_rounding() {
+        let uq4_2 = QFormat::unsigned(4, 2);
+        let mut target = vec![0u8];
+        uq4_2.write_f64(&mut target, 0, 3.13).unwrap();
+        assert_eq!(target, [0b00110100]);
+        assert_eq!(uq4_2.read_f64(&target, 0), 3.25);
+    }
+}
+
+/// Fixed-point number of a [`QFormat`], the raw value is always
+/// in the range of the format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fixed {
+    raw: i128,
+    format: QFormat,
+}
+
+impl Fixed {
+    /// `None` if `raw` is out of the range of the format
+    pub fn from_raw(format: QFormat, raw: i128) -> Option<Self> {
+        format.is_raw_in_range(raw).then_some(Self { raw, format })
+    }
+
+    /// `value` rounded to the nearest number of the format, `None`
+    /// if it is out of the range of the format or NaN
+    pub fn from_f64(format: QFormat, value: f64) -> Option<Self> {
+        if value.is_nan() {
+            return None;
+        }
+        // NOTE: The cast saturates, so too large values stay out of range
+        let raw = (value * (format.frac_bits as f64).exp2()).round() as i128;
+        Self::from_raw(format, raw)
+    }
+
+    pub fn from_f32(format: QFormat, value: f32) -> Option<Self> {
+        Self::from_f64(format, value as f64)
+    }
+
+    pub fn raw(&self) -> i128 {
+        self.raw
+    }
+
+    pub fn format(&self) -> QFormat {
+        self.format
+    }
+
+    /// **NOTE**: Raw values wider than 53 bits are rounded
+    pub fn to_f64(&self) -> f64 {
+        self.raw as f64 * self.format.resolution()
+    }
+
+    /// **NOTE**: Raw values wider than 24 bits are rounded
+    pub fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    /// Writes the raw value to target by bit offset
+    ///
+    /// **PANIC**: If the target is too small
+    ///
+    /// **NOTE**: The raw value is ORed into the target as by [`bit_write`],
+    /// so the field is expected to be clean
+    pub fn write<T>(&self, target: &mut T, target_bit_offset: usize)
+    where
+        T: IndexMut<usize, Output = u8>,
+    {
+        let source = (self.raw as u64).to_be_bytes();
+        bit_write(
+            target,
+            target_bit_offset,
+            self.format.bit_size(),
+            &source,
+            source.len(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_fixed {
+    use super::*;
+
+    #[test]
+    fn check_raw() {
+        let q1_6 = QFormat::signed(1, 6);
+        assert_eq!(
+            Fixed::from_raw(q1_6, -128).map(|fixed| fixed.to_f64()),
+            Some(-2.0)
+        );
+        assert_eq!(Fixed::from_raw(q1_6, 128), None);
+        assert_eq!(
+            Fixed::from_f64(q1_6, 0.25).map(|fixed| fixed.raw()),
+            Some(16)
+        );
+        assert_eq!(Fixed::from_f32(q1_6, 2.0), None);
+        assert_eq!(Fixed::from_f64(q1_6, f64::INFINITY), None);
+    }
+
+    #[test]
+    fn check_write_read() {
+        let q7_24 = QFormat::signed(7, 24);
+        let fixed = Fixed::from_f64(q7_24, -100.125).unwrap();
+        let mut target = vec![0u8; 5];
+        fixed.write(&mut target, 5);
+        assert_eq!(q7_24.read(&target, 5), fixed);
+
+        let uq64 = QFormat::unsigned(0, 64);
+        let fixed = Fixed::from_raw(uq64, u64::MAX as i128).unwrap();
+        let mut target = vec![0u8; 8];
+        fixed.write(&mut target, 0);
+        assert_eq!(uq64.read(&target, 0), fixed);
+
+        let q63 = QFormat::signed(0, 63);
+        let fixed = Fixed::from_raw(q63, i64::MIN as i128).unwrap();
+        let mut target = vec![0u8; 8];
+        fixed.write(&mut target, 0);
+        assert_eq!(q63.read(&target, 0).to_f64(), -1.0);
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::IndexMut;
+
+use crate::logic::bit_fill;
+
+/// Primitive integer, that can be stored in a bit field of a width
+/// up to its own size. Signed integers are stored in two's complement,
+/// that is the sign bit is counted in the width
+pub trait BitInt: Copy + PartialOrd {
+    /// Size of the type in bits
+    const BITS: u32;
+    /// Whether the type is signed
+    const SIGNED: bool;
+
+    /// Maximum value that `bit_size` bits can store
+    fn max_hold(bit_size: u32) -> Self;
+
+    /// Minimum value that `bit_size` bits can store
+    fn min_hold(bit_size: u32) -> Self;
+}
+
+macro_rules! impl_bit_int {
+    ($signed:literal => $($ty:ty),*) => {
+        $(
+            impl BitInt for $ty {
+                const BITS: u32 = <$ty>::BITS;
+                const SIGNED: bool = $signed;
+
+                fn max_hold(bit_size: u32) -> Self {
+                    if bit_size == 0 {
+                        return 0;
+                    }
+                    <$ty>::MAX >> (Self::BITS - bit_size)
+                }
+
+                fn min_hold(bit_size: u32) -> Self {
+                    if bit_size == 0 {
+                        return 0;
+                    }
+                    // NOTE: For signed types it is an arithmetic shift,
+                    // so the sign bit spreads to the right
+                    <$ty>::MIN >> (Self::BITS - bit_size)
+                }
+            }
+        )*
+    };
+}
+
+impl_bit_int!(false => u8, u16, u32, u64, u128, usize);
+impl_bit_int!(true => i8, i16, i32, i64, i128, isize);
+
+/// Maximum value that `bit_size` bits can store in the type `I`
+///
+/// **PANIC**: If `bit_size` large than size of `I`
+pub fn max_hold<I: BitInt>(bit_size: u32) -> I {
+    assert!(bit_size <= I::BITS, "bit_size large than type bit size");
+    I::max_hold(bit_size)
+}
+
+#[cfg(test)]
+mod tests_max_hold {
+    use super::*;
+
+    #[test]
+    fn check_unsigned() {
+        assert_eq!(max_hold::<u8>(0), 0);
+        assert_eq!(max_hold::<u8>(6), 63);
+        assert_eq!(max_hold::<u32>(32), u32::MAX);
+        assert_eq!(max_hold::<u64>(33), (1 << 33) - 1);
+        assert_eq!(max_hold::<u128>(128), u128::MAX);
+    }
+
+    #[test]
+    fn check_signed() {
+        assert_eq!(max_hold::<i8>(1), 0);
+        assert_eq!(max_hold::<i16>(12), 2047);
+        assert_eq!(max_hold::<i128>(128), i128::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_too_wide() {
+        max_hold::<u16>(17);
+    }
+}
+
+/// Minimum value that `bit_size` bits can store in the type `I`,
+/// zero for unsigned types
+///
+/// **PANIC**: If `bit_size` large than size of `I`
+pub fn min_hold<I: BitInt>(bit_size: u32) -> I {
+    assert!(bit_size <= I::BITS, "bit_size large than type bit size");
+    I::min_hold(bit_size)
+}
+
+#[cfg(test)]
+mod tests_min_hold {
+    use super::*;
+
+    #[test]
+    fn check_unsigned() {
+        assert_eq!(min_hold::<u64>(64), 0);
+    }
+
+    #[test]
+    fn check_signed() {
+        assert_eq!(min_hold::<i8>(0), 0);
+        assert_eq!(min_hold::<i8>(1), -1);
+        assert_eq!(min_hold::<i16>(12), -2048);
+        assert_eq!(min_hold::<i64>(64), i64::MIN);
+        assert_eq!(min_hold::<i128>(100), -(1 << 99));
+    }
+}
+
+/// Writes to target the maximum value that `bit_size` bits can store,
+/// that is sets `bit_size` least significant bits. The target is
+/// considered as a big-endian number of `byte_target_len` bytes
+///
+/// **PANIC**: If `bit_size` large than target bit size
+///
+/// **NOTE**: Bits are only set, the more significant bits of
+/// the target are kept
+pub fn max_hold_bytes<T>(bit_size: usize, target: &mut T, byte_target_len: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    assert!(
+        bit_size <= byte_target_len * 8,
+        "bit_size large than target bit size"
+    );
+
+    bit_fill(target, byte_target_len * 8 - bit_size, bit_size);
+}
+
+#[cfg(test)]
+mod tests_max_hold_bytes {
+    use super::*;
+
+    #[test]
+    fn check_partial() {
+        let mut target = [0u8; 3];
+        max_hold_bytes(11, &mut target, 3);
+        assert_eq!(target, [0b00000000, 0b00000111, 0b11111111]);
+    }
+
+    #[test]
+    fn check_wide() {
+        let mut target = [0u8; 20];
+        max_hold_bytes(150, &mut target, 20);
+
+        let mut expected = [0b11111111u8; 20];
+        expected[0] = 0b00000000;
+        expected[1] = 0b00111111;
+        assert_eq!(target, expected);
+    }
+}
+
+/// Typed counterpart of [`is_in_range`](crate::is_in_range). Checks that
+/// `value` can be stored in `bit_size` bits, for signed types it
+/// includes the sign bit
+///
+/// **PANIC**: If `bit_size` large than size of `I`
+pub fn is_value_in_range<I: BitInt>(bit_size: u32, value: I) -> bool {
+    min_hold::<I>(bit_size) <= value && value <= max_hold::<I>(bit_size)
+}
+
+#[cfg(test)]
+mod tests_is_value_in_range {
+    use super::*;
+
+    #[test]
+    fn check_unsigned() {
+        assert!(is_value_in_range(5, 31u8));
+        assert!(!is_value_in_range(5, 32u8));
+        assert!(is_value_in_range(0, 0u32));
+        assert!(!is_value_in_range(0, 1u32));
+        assert!(is_value_in_range(128, u128::MAX));
+        assert!(!is_value_in_range(127, u128::MAX));
+    }
+
+    #[test]
+    fn check_signed() {
+        assert!(is_value_in_range(4, -8i32));
+        assert!(is_value_in_range(4, 7i32));
+        assert!(!is_value_in_range(4, -9i32));
+        assert!(!is_value_in_range(4, 8i32));
+        assert!(is_value_in_range(64, i64::MIN));
+    }
+}
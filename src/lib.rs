@@ -1,13 +1,39 @@
 use std::ops::{Index, IndexMut};
 
+pub mod atomic;
+pub mod buf;
+pub mod checked;
+pub mod codec;
+pub mod cmp;
+pub mod coverage;
+pub mod fixed;
+pub mod int;
+pub mod logic;
+pub mod packed;
+pub mod policy;
+pub mod quantize;
+pub mod radix;
+pub mod reverse;
+pub mod scan;
+pub mod search;
+pub mod shift;
+pub mod simd;
+pub mod slice;
+pub mod split;
+pub mod validate;
+
 /// Maximum value that N bits can store
+///
+/// **PANIC**: If `bit_size` large than 32, see [`int::max_hold`]
+/// for wider types
 pub fn bits_to_max_hold(bit_size: u32) -> u32 {
-    (1 << bit_size) - 1
+    int::max_hold(bit_size)
 }
 
 #[test]
 fn check_bits_to_max_hold() {
     assert_eq!(63, bits_to_max_hold(6));
+    assert_eq!(u32::MAX, bits_to_max_hold(32));
 }
 
 /// Checks that's current stored value, 
@@ -80,12 +106,13 @@ pub fn is_in_range<'i>(
             //   ---
             //      \
             //       and should be equal to 0
-            return (byte & 0b11111111 << (8 - empty_in_start_of_pf) ) == 0 
+            return (byte & 0b11111111u8.checked_shl(8 - empty_in_start_of_pf as u32).unwrap_or_default()) == 0
         }
     }   
     unreachable!()
 }
 
+#[cfg(test)]
 mod tests_is_in_range {
     use super::*;
     
@@ -101,6 +128,12 @@ mod tests_is_in_range {
         let source = [0b00001000u8, 0b00011111u8];
         assert!(!is_in_range(5, &source, source.len()));
     }
+
+    #[test]
+    fn check_full_width() {
+        let source = [0b11111111u8, 0b11111111u8];
+        assert!(is_in_range(16, &source, source.len()));
+    }
 }
 
 /// Writes N bits from source to target by bit offset
@@ -119,8 +152,8 @@ pub fn bit_write<T, S>(
     source: &S,
     byte_source_len: usize,
 ) where
-    T: IndexMut<usize, Output = u8>,
-    S: Index<usize, Output = u8>,
+    T: IndexMut<usize, Output = u8> + ?Sized,
+    S: Index<usize, Output = u8> + ?Sized,
 {
     if recordable_bit_size == 0 {
         return;
@@ -166,17 +199,11 @@ pub fn bit_write<T, S>(
         affected_bytes_num += remainder / 8;
 
         // If exist remainder, add last partially affected byte
-        if remainder % 8 > 0 {
+        if !remainder.is_multiple_of(8) {
             affected_bytes_num += 1;
         }
     }
 
-    // We calculate the length of the written body in bytes (rounding up)
-    let mut meaningful_len = recordable_bit_size / 8;
-    if recordable_bit_size % 8 > 0 {
-        meaningful_len += 1;
-    }
-
     // Counter of the number of slots already occupied 
     // in the current byte. Here we initialize for
     // first partially affected byte
@@ -199,51 +226,18 @@ pub fn bit_write<T, S>(
     let iter_range = start_byte_index..last_byte_index;
     for target_index in iter_range {
         loop {
-       
-            // Calculate index of first byte being written
-            // NOTE: `source_len` can be bigger than `meaningful_len`, 
-            // so the most significant byte is simply discarded. 
-            let mut source_index = byte_source_len - meaningful_len;
-            
-            // Number of bits already written
-            let already_written = recordable_bit_size - cursor;
-
-            /*
-                CALCULATE now current index of byte being written
-            */
-
-            // If `already_written` === 0, we haven't recorded anything yet.
-            //
-            // `already_written` >= `bit_size` % 8, handle that we write
-            // affected significant bit from first partially affected byte
-            // from source
+            // The remaining `cursor` bits are the least significant bits
+            // of the source, so the byte being written is the one holding
+            // the most significant of them (rounding up)
             //
-            //  |b|b|pa|a|a|a|pa|b|b| <--- target
-            //       ^
-            //       |------        --- bit from source thats we write
-            //              \      /
-            //              -----------
-            //   ... # |1|0|1|1|0|0|1|1| # |1|0|1|1|0|0|1|1| # ... <-- source
-            //              -------------
-            //              |
-            //              bit for which will be recorded
-            //              from first partially affected byte
+            //   ... # |1|0|1|1|0|0|1|1| # |1|0|1|1|0|0|1|1| <-- source
+            //                  -------------------------
+            //                  |
+            //                  `cursor` bits remaining for recording
             //
-            //
-            if already_written >= recordable_bit_size % 8 && already_written > 0 {
-                // If record bit from first partially affected byte
-                // from source add to index one
-                source_index += 1;
-
-                // If you wrote more than 1 byte, add the remaining ones
-                if already_written / 8 > 1 {
-                    source_index += already_written / 8 - 1
-                }
-            }
-
-            /*
-                CALCULATE END
-            */
+            // NOTE: `source_len` can be bigger than the written body,
+            // so the most significant bytes are simply discarded.
+            let source_index = byte_source_len - cursor.div_ceil(8);
 
             // The available number of slots to which we will write 
             // in the current byte in the TARGET
@@ -253,7 +247,7 @@ pub fn bit_write<T, S>(
             // The calculation algorithm is as follows if the remainder is zero. 
             // We can write a whole byte, if there is a remainder, then it is 
             // equal to the number of slots that be printed to target
-            let available_for_print = if cursor % 8 != 0 { cursor % 8 } else { 8 };
+            let available_for_print = if !cursor.is_multiple_of(8) { cursor % 8 } else { 8 };
 
             let write_size;
             
@@ -356,6 +350,7 @@ pub fn bit_write<T, S>(
     }
 }
 
+#[cfg(test)]
 mod tests_bit_write {
     use super::*;
 
@@ -382,6 +377,18 @@ mod tests_bit_write {
         bit_write(&mut target, 3, 3, &source, source.len());
         assert_eq!(target, [0b00011100, 0b00000000]);
     }
+
+    #[test]
+    fn check_long_unaligned() {
+        let mut target = [0u8; 5];
+        let source = [0b11111111, 0b10110011, 0b10001111, 0b01010101];
+
+        bit_write(&mut target, 5, 29, &source, source.len());
+        assert_eq!(
+            target,
+            [0b00000111, 0b11101100, 0b11100011, 0b11010101, 0b01000000]
+        );
+    }
 }
 
 /// Reset bits to zero in the range of `bit_size` 
@@ -391,7 +398,7 @@ pub fn bit_clean<T>(
     bit_offset: usize,
     bit_size: usize,
 ) where
-    T: IndexMut<usize, Output = u8>
+    T: IndexMut<usize, Output = u8> + ?Sized,
 {
     if bit_size == 0 {
         return;
@@ -404,7 +411,7 @@ pub fn bit_clean<T>(
     let remainder = bit_size.saturating_sub(slots_at_start_byte);
     if remainder != 0 {
         affected_bytes_num += remainder / 8;
-        if remainder % 8 > 0 {
+        if !remainder.is_multiple_of(8) {
             affected_bytes_num += 1;
         }
     }
@@ -457,7 +464,7 @@ pub fn bit_clean<T>(
         if target_index ==  start_byte_index {
             mask = 0b11111111u8.checked_shl(slots_at_start_byte as u32).unwrap_or_default(); 
         }
-        if target_index == last_byte_index - 1 && remainder % 8 > 0 {
+        if target_index == last_byte_index - 1 && !remainder.is_multiple_of(8) {
             let slots_at_last_byte = remainder - remainder/8*8;
             mask = 0b11111111 >> slots_at_last_byte;
         }
@@ -466,6 +473,7 @@ pub fn bit_clean<T>(
     }
 }
 
+#[cfg(test)]
 mod tests_bit_clean {
     use super::*;
 
@@ -554,8 +562,8 @@ pub fn membitcpy<T, S>(
     source: &S,
     source_bit_offset: usize,
 ) where
-    T: IndexMut<usize, Output = u8>,
-    S: Index<usize, Output = u8>,
+    T: IndexMut<usize, Output = u8> + ?Sized,
+    S: Index<usize, Output = u8> + ?Sized,
 {
     if recordable_bit_size == 0 {
         return;
@@ -596,7 +604,7 @@ pub fn membitcpy<T, S>(
         affected_bytes_num += remainder / 8;
 
         // If exist remainder, add last partially affected byte
-        if remainder % 8 > 0 {
+        if !remainder.is_multiple_of(8) {
             affected_bytes_num += 1;
         }
     }
@@ -654,12 +662,11 @@ pub fn membitcpy<T, S>(
             let source_lhs_shift = (source_bit_offset + already_written) % 8;
 
             // Available for printing bit slots from SOURCE!
-            let available_for_print;
-            if 8 - source_lhs_shift >= cursor {
-                available_for_print = cursor;
+            let available_for_print = if 8 - source_lhs_shift >= cursor {
+                cursor
             } else {
-                available_for_print = 8 - source_lhs_shift;
-            }
+                8 - source_lhs_shift
+            };
 
             let write_size;
             
@@ -717,6 +724,7 @@ pub fn membitcpy<T, S>(
     }
 }
 
+#[cfg(test)]
 mod tests_membitcpy {
     use super::*;
 
@@ -807,8 +815,8 @@ pub fn bit_read<T, S>(
     target: &mut T,
     byte_target_len: usize,
 ) where
-    T: IndexMut<usize, Output = u8>,
-    S: Index<usize, Output = u8>,
+    T: IndexMut<usize, Output = u8> + ?Sized,
+    S: Index<usize, Output = u8> + ?Sized,
 {
     if recordable_bit_size == 0 {
         return;
@@ -883,6 +891,7 @@ pub fn bit_read<T, S>(
     }
 }
 
+#[cfg(test)]
 mod tests_bit_read {
     use super::*;
 
@@ -963,4 +972,115 @@ mod tests_bit_read {
         bit_read(&source, 8*2, 8*3, &mut target, target_len);
         assert_eq!(target, [0b11111111, 0b11111111, 0b11111111]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn check_long_unaligned() {
+        let source = [0b00000111, 0b11101100, 0b11100011, 0b11010101, 0b01000000];
+        let mut target = [0u8; 4];
+        let target_len = target.len();
+
+        bit_read(&source, 5, 29, &mut target, target_len);
+        assert_eq!(target, [0b00011111, 0b10110011, 0b10001111, 0b01010101]);
+    }
+}
+
+/// Moves `recordable_bit_size` bits of buffer by offset `source_bit_offset`
+/// to offset `target_bit_offset` of the same buffer. The ranges may overlap
+/// (like `memmove`, but for bits)
+///
+/// **PANIC**: If out of range buffer
+///
+/// **NOTE**: Unlike [`membitcpy`], the target bits are replaced, since
+/// they may still hold the source bits
+pub fn membitmove<T>(
+    buffer: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source_bit_offset: usize,
+) where
+    T: IndexMut<usize, Output = u8> + ?Sized,
+{
+    // Move by chunks no longer than a `u64`. When the target is ahead
+    // of the source, go from the start, so every chunk is read before
+    // it is overwritten, otherwise go from the end
+    let forward = target_bit_offset <= source_bit_offset;
+
+    let mut moved = 0;
+    while moved < recordable_bit_size {
+        let width = (recordable_bit_size - moved).min(64);
+        let done = if forward {
+            moved
+        } else {
+            recordable_bit_size - moved - width
+        };
+
+        let chunk = read_bits(buffer, source_bit_offset + done, width).to_be_bytes();
+        bit_clean(buffer, target_bit_offset + done, width);
+        bit_write(buffer, target_bit_offset + done, width, &chunk, chunk.len());
+
+        moved += width;
+    }
+}
+
+#[cfg(test)]
+mod tests_membitmove {
+    use super::*;
+
+    #[test]
+    fn check_to_start() {
+        let mut buffer = [0b00011011u8, 0b11000000u8];
+        membitmove(&mut buffer, 1, 7, 3);
+        assert_eq!(buffer, [0b01101111, 0b11000000]);
+    }
+
+    #[test]
+    fn check_to_end() {
+        let mut buffer = [0b10110000u8, 0b11111111u8];
+        membitmove(&mut buffer, 3, 4, 0);
+        assert_eq!(buffer, [0b10110110, 0b11111111]);
+    }
+
+    #[test]
+    fn check_unsized() {
+        let mut buffer = vec![0b10110000u8, 0b11111111u8];
+        membitmove(&mut buffer[..], 3, 4, 0);
+        assert_eq!(buffer, [0b10110110, 0b11111111]);
+    }
+
+    #[test]
+    fn check_long_overlap() {
+        let source = test_bytes(30, 0);
+
+        let mut forward = source.clone();
+        membitmove(&mut forward, 5, 200, 9);
+        let mut expected = source.clone();
+        bit_clean(&mut expected, 5, 200);
+        membitcpy(&mut expected, 5, 200, &source, 9);
+        assert_eq!(forward, expected);
+
+        let mut backward = source.clone();
+        membitmove(&mut backward, 9, 200, 5);
+        let mut expected = source.clone();
+        bit_clean(&mut expected, 9, 200);
+        membitcpy(&mut expected, 9, 200, &source, 5);
+        assert_eq!(backward, expected);
+    }
+}
+
+// Reads `width` (up to 64) bits at `bit_offset` as a right aligned `u64`
+pub(crate) fn read_bits<S>(source: &S, bit_offset: usize, width: usize) -> u64
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    let mut target = [0u8; 8];
+    let target_len = target.len();
+    bit_read(source, bit_offset, width, &mut target, target_len);
+    u64::from_be_bytes(target)
+}
+
+// Bytes of an irregular pattern, that tests use as buffers, `seed`
+// gives another pattern of the same length
+#[cfg(test)]
+pub(crate) fn test_bytes(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|index| (index * 167 + index / 7) as u8 ^ seed).collect()
+}
@@ -0,0 +1,293 @@
+use std::ops::{Index, IndexMut, Range};
+
+use crate::read_bits;
+
+/// ANDs `recordable_bit_size` bits of source at `source_bit_offset`
+/// into target at `target_bit_offset`
+///
+/// **PANIC**: If out of range target or source
+pub fn bit_and<T, S>(
+    target: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    source_bit_offset: usize,
+) where
+    T: IndexMut<usize, Output = u8>,
+    S: Index<usize, Output = u8>,
+{
+    apply_with_source(
+        target,
+        target_bit_offset,
+        recordable_bit_size,
+        source,
+        source_bit_offset,
+        |target, source| target & source,
+    );
+}
+
+#[cfg(test)]
+mod tests_bit_and {
+    use super::*;
+
+    #[test]
+    fn check_unaligned() {
+        let mut target = [0b11111111u8, 0b11111111u8];
+        let source = [0b01010101u8, 0b01000000u8];
+        bit_and(&mut target, 3, 10, &source, 1);
+        assert_eq!(target, [0b11110101, 0b01010111]);
+    }
+
+    #[test]
+    fn check_small() {
+        let mut target = [0b11111111u8];
+        let source = [0b00000000u8];
+        bit_and(&mut target, 2, 3, &source, 0);
+        assert_eq!(target, [0b11000111]);
+    }
+}
+
+/// ORs `recordable_bit_size` bits of source at `source_bit_offset`
+/// into target at `target_bit_offset`, the same as [`membitcpy`](crate::membitcpy)
+///
+/// **PANIC**: If out of range target or source
+pub fn bit_or<T, S>(
+    target: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    source_bit_offset: usize,
+) where
+    T: IndexMut<usize, Output = u8>,
+    S: Index<usize, Output = u8>,
+{
+    apply_with_source(
+        target,
+        target_bit_offset,
+        recordable_bit_size,
+        source,
+        source_bit_offset,
+        |target, source| target | source,
+    );
+}
+
+#[cfg(test)]
+mod tests_bit_or {
+    use super::*;
+
+    #[test]
+    fn check_unaligned() {
+        let mut target = [0b00000000u8, 0b00000001u8];
+        let source = [0b01010101u8, 0b01000000u8];
+        bit_or(&mut target, 3, 10, &source, 1);
+        assert_eq!(target, [0b00010101, 0b01010001]);
+    }
+}
+
+/// XORs `recordable_bit_size` bits of source at `source_bit_offset`
+/// into target at `target_bit_offset`
+///
+/// **PANIC**: If out of range target or source
+pub fn bit_xor<T, S>(
+    target: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    source_bit_offset: usize,
+) where
+    T: IndexMut<usize, Output = u8>,
+    S: Index<usize, Output = u8>,
+{
+    apply_with_source(
+        target,
+        target_bit_offset,
+        recordable_bit_size,
+        source,
+        source_bit_offset,
+        |target, source| target ^ source,
+    );
+}
+
+#[cfg(test)]
+mod tests_bit_xor {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_unaligned() {
+        let mut target = [0b11110000u8, 0b11110000u8];
+        let source = [0b01010101u8, 0b01000000u8];
+        bit_xor(&mut target, 3, 10, &source, 1);
+        assert_eq!(target, [0b11100101, 0b10100000]);
+    }
+
+    #[test]
+    fn check_against_bits() {
+        let origin = test_bytes(300, 0b01011010);
+        let source = test_bytes(300, 0);
+
+        for (target_bit_offset, bit_size, source_bit_offset) in [
+            (0, 2400, 0),
+            (3, 2000, 11),
+            (8, 1234, 5),
+            (6, 1, 0),
+            (13, 60, 2),
+        ] {
+            let mut target = origin.clone();
+            bit_xor(
+                &mut target,
+                target_bit_offset,
+                bit_size,
+                &source,
+                source_bit_offset,
+            );
+
+            let mut expected = origin.clone();
+            for index in 0..bit_size {
+                let bit_index = source_bit_offset + index;
+                if source[bit_index / 8] & (0b10000000 >> (bit_index % 8)) != 0 {
+                    let bit_index = target_bit_offset + index;
+                    expected[bit_index / 8] ^= 0b10000000 >> (bit_index % 8);
+                }
+            }
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn check_long() {
+        let mut target = [0b10101010u8; 5];
+        let source = [0b10101010u8; 5];
+        bit_xor(&mut target, 4, 32, &source, 4);
+        assert_eq!(
+            target,
+            [0b10100000, 0b00000000, 0b00000000, 0b00000000, 0b00001010]
+        );
+    }
+}
+
+/// Inverts `bit_size` bits of target at `bit_offset`
+pub fn bit_not<T>(target: &mut T, bit_offset: usize, bit_size: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    apply(target, bit_offset, bit_size, |target, _| !target);
+}
+
+#[cfg(test)]
+mod tests_bit_not {
+    use super::*;
+
+    #[test]
+    fn check_intersection() {
+        let mut target = [0b00000111u8, 0b11111111u8, 0b11100000u8];
+        bit_not(&mut target, 5, 14);
+        assert_eq!(target, [0b00000000, 0b00000000, 0b00000000]);
+    }
+}
+
+/// Sets `bit_size` bits of target at `bit_offset` to one,
+/// the counterpart of [`bit_clean`](crate::bit_clean)
+pub fn bit_fill<T>(target: &mut T, bit_offset: usize, bit_size: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    apply(target, bit_offset, bit_size, |_, _| 0b11111111);
+}
+
+#[cfg(test)]
+mod tests_bit_fill {
+    use super::*;
+
+    #[test]
+    fn check_intersection() {
+        let mut target = [0u8; 4];
+        bit_fill(&mut target, 5, 3 + 8 + 3);
+        assert_eq!(target, [0b00000111, 0b11111111, 0b11100000, 0b00000000]);
+    }
+
+    #[test]
+    fn check_one_byte_small() {
+        let mut target = [0u8; 2];
+        bit_fill(&mut target, 11, 3);
+        assert_eq!(target, [0b00000000, 0b00011100]);
+    }
+}
+
+// Applies `op(target_byte, source_bits)` to the range, where the source
+// bits are placed at the same positions as the affected target bits. Only
+// the partial bytes at the edges are masked, for the whole bytes between
+// them the source is read by words
+fn apply_with_source<T, S>(
+    target: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    source_bit_offset: usize,
+    op: impl Fn(u8, u8) -> u8,
+) where
+    T: IndexMut<usize, Output = u8>,
+    S: Index<usize, Output = u8>,
+{
+    // Source bits of the target bits in `range`, right aligned
+    let read = |range: Range<usize>| {
+        let source_index = source_bit_offset + (range.start - target_bit_offset);
+        read_bits(source, source_index, range.len())
+    };
+    let edge_op = |byte, range: Range<usize>| {
+        let shift = (8 - range.end % 8) % 8;
+        op(byte, (read(range) as u8) << shift)
+    };
+
+    // Bits before the first whole byte of the target and after the last one
+    let head = ((8 - target_bit_offset % 8) % 8).min(recordable_bit_size);
+    let bytes_num = (recordable_bit_size - head) / 8;
+    let tail = recordable_bit_size - head - bytes_num * 8;
+
+    apply(target, target_bit_offset, head, edge_op);
+
+    let body_bit_offset = target_bit_offset + head;
+    let mut done = 0;
+    while done < bytes_num {
+        let len = (bytes_num - done).min(8);
+        let bit_index = body_bit_offset + done * 8;
+        let bits = read(bit_index..bit_index + len * 8) << (64 - len * 8);
+
+        for (index, bits) in bits.to_be_bytes()[..len].iter().enumerate() {
+            let byte = &mut target[bit_index / 8 + index];
+            *byte = op(*byte, *bits);
+        }
+        done += len;
+    }
+
+    apply(target, body_bit_offset + bytes_num * 8, tail, edge_op);
+}
+
+// Replaces every affected byte of the target with `op(target_byte, range)`
+// under the mask of the range, where `range` is the affected range of the
+// byte in bits of the target
+fn apply<T>(target: &mut T, bit_offset: usize, bit_size: usize, op: impl Fn(u8, Range<usize>) -> u8)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    let mut done = 0;
+    while done < bit_size {
+        let bit_index = bit_offset + done;
+        let from = bit_index % 8;
+        let to = (from + bit_size - done).min(8);
+
+        // For example `from` is 2 and `to` is 5, then
+        //
+        // |0|0|1|1|1|0|0|0|
+        //      -----
+        let mask =
+            (0b11111111u8 >> from) & !0b11111111u8.checked_shr(to as u32).unwrap_or_default();
+
+        let index = bit_index / 8;
+        let byte = target[index];
+        let range = bit_index..bit_index + (to - from);
+        target[index] = (byte & !mask) | (op(byte, range) & mask);
+
+        done += to - from;
+    }
+}
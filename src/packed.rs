@@ -0,0 +1,606 @@
+use std::fmt;
+use std::ops::{Index, IndexMut, Range, RangeBounds};
+
+use crate::slice::to_range;
+use crate::{bit_clean, bit_write, read_bits};
+
+/// Vector of unsigned integers of `width` bits (up to 64), that are stored
+/// back to back without padding. The width is chosen at runtime, see
+/// [`PackedVec`] for the compile time one
+///
+/// **NOTE**: Bits of the values that are out of `width` are discarded
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct DynPackedVec {
+    bytes: Vec<u8>,
+    width: usize,
+    len: usize,
+}
+
+impl DynPackedVec {
+    /// **PANIC**: If `width` is zero or large than 64
+    pub fn new(width: usize) -> Self {
+        Self::with_capacity(width, 0)
+    }
+
+    /// Empty vector with space for at least `capacity` values
+    ///
+    /// **PANIC**: If `width` is zero or large than 64
+    pub fn with_capacity(width: usize, capacity: usize) -> Self {
+        assert!(width != 0 && width <= 64, "width out of 1..=64");
+        Self {
+            bytes: Vec::with_capacity((capacity * width).div_ceil(8)),
+            width,
+            len: 0,
+        }
+    }
+
+    /// Vector of all `values`
+    ///
+    /// **PANIC**: If `width` is zero or large than 64
+    pub fn from_slice(width: usize, values: &[u64]) -> Self {
+        let mut vec = Self::with_capacity(width, values.len());
+        vec.extend(values.iter().copied());
+        vec
+    }
+
+    /// Width of the values in bits
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Underlying bytes, the unused bits of the last byte are zero
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Value at `index`, `None` if out of the vector
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index >= self.len {
+            return None;
+        }
+        Some(get_value(&self.bytes, self.width, index))
+    }
+
+    /// Replaces the value at `index`
+    ///
+    /// **PANIC**: If `index` out of the vector
+    pub fn set(&mut self, index: usize, value: u64) {
+        assert!(index < self.len, "index out of the vector");
+        set_value(&mut self.bytes, self.width, index, value);
+    }
+
+    /// Appends a value
+    pub fn push(&mut self, value: u64) {
+        self.len += 1;
+        self.bytes.resize((self.len * self.width).div_ceil(8), 0);
+        set_value(&mut self.bytes, self.width, self.len - 1, value);
+    }
+
+    /// Removes the last value and returns it, `None` if the vector is empty
+    pub fn pop(&mut self) -> Option<u64> {
+        let value = self.get(self.len.checked_sub(1)?)?;
+        self.truncate(self.len - 1);
+        Some(value)
+    }
+
+    /// Shortens the vector to `len` values, has no effect
+    /// if the vector is already shorter
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let bit_len = len * self.width;
+        bit_clean(&mut self.bytes, bit_len, (self.len - len) * self.width);
+        self.bytes.truncate(bit_len.div_ceil(8));
+        self.len = len;
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        self.as_packed_slice().iter()
+    }
+
+    pub fn as_packed_slice(&self) -> PackedSlice<'_> {
+        PackedSlice {
+            bytes: &self.bytes,
+            width: self.width,
+            start: 0,
+            len: self.len,
+        }
+    }
+
+    /// View of the values in `range`
+    ///
+    /// **PANIC**: If `range` out of the vector
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> PackedSlice<'_> {
+        self.as_packed_slice().slice(range)
+    }
+
+    /// Copies all values to a new `Vec`
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.iter().collect()
+    }
+}
+
+impl Extend<u64> for DynPackedVec {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a DynPackedVec {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for DynPackedVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests_dyn_packed_vec {
+    use super::*;
+
+    #[test]
+    fn check_push_get() {
+        let mut vec = DynPackedVec::new(11);
+        vec.push(0b10101010101);
+        vec.push(0b00000000001);
+        vec.push(0b11111111111);
+
+        assert_eq!(vec.len(), 3);
+        assert_eq!(
+            vec.as_bytes(),
+            [0b10101010, 0b10100000, 0b00000111, 0b11111111, 0b10000000]
+        );
+        assert_eq!(vec.get(0), Some(0b10101010101));
+        assert_eq!(vec.get(2), Some(0b11111111111));
+        assert_eq!(vec.get(3), None);
+    }
+
+    #[test]
+    fn check_set() {
+        let mut vec = DynPackedVec::from_slice(13, &[1, 2, 3, 4]);
+        vec.set(1, 8191);
+        vec.set(2, 0);
+        assert_eq!(vec.to_vec(), [1, 8191, 0, 4]);
+
+        // Bits out of the width are discarded
+        vec.set(3, 8192 + 5);
+        assert_eq!(vec.get(3), Some(5));
+    }
+
+    #[test]
+    fn check_fast_path() {
+        for width in [1, 2, 4, 8, 16, 32, 64] {
+            let values: Vec<u64> = (0..100u64)
+                .map(|index| index.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - width))
+                .collect();
+            let mut vec = DynPackedVec::from_slice(width, &values);
+            assert_eq!(vec.to_vec(), values);
+
+            vec.set(7, 1);
+            assert_eq!(vec.get(6), Some(values[6]));
+            assert_eq!(vec.get(7), Some(1));
+            assert_eq!(vec.get(8), Some(values[8]));
+        }
+    }
+
+    #[test]
+    fn check_pop_truncate() {
+        let mut vec = DynPackedVec::from_slice(5, &[31, 1, 2]);
+        assert_eq!(vec.pop(), Some(2));
+        vec.truncate(1);
+        assert_eq!(vec.as_bytes(), [0b11111000]);
+        vec.clear();
+        assert_eq!(vec.pop(), None);
+        assert_eq!(
+            format!("{:?}", DynPackedVec::from_slice(3, &[1, 7])),
+            "[1, 7]"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_zero_width() {
+        DynPackedVec::new(0);
+    }
+}
+
+/// Vector of unsigned integers of `W` bits (up to 64), that are stored
+/// back to back without padding. When `W` divides 64 the values never
+/// cross a word and are accessed without the general bit routines, the
+/// choice is made at compile time
+///
+/// **NOTE**: Bits of the values that are out of `W` are discarded, and
+/// `W` out of `1..=64` fails to compile
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PackedVec<const W: usize> {
+    inner: DynPackedVec,
+}
+
+impl<const W: usize> PackedVec<W> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Empty vector with space for at least `capacity` values
+    pub fn with_capacity(capacity: usize) -> Self {
+        const { assert!(W != 0 && W <= 64, "W out of 1..=64") };
+        Self {
+            inner: DynPackedVec::with_capacity(W, capacity),
+        }
+    }
+
+    /// Vector of all `values`
+    pub fn from_slice(values: &[u64]) -> Self {
+        let mut vec = Self::with_capacity(values.len());
+        vec.extend(values.iter().copied());
+        vec
+    }
+
+    /// Number of values
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Underlying bytes, the unused bits of the last byte are zero
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    /// Value at `index`, `None` if out of the vector
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index >= self.len() {
+            return None;
+        }
+        match 64 % W {
+            0 => Some(get_aligned_value(&self.inner.bytes, W, index)),
+            _ => Some(read_bits(&self.inner.bytes, index * W, W)),
+        }
+    }
+
+    /// Replaces the value at `index`
+    ///
+    /// **PANIC**: If `index` out of the vector
+    pub fn set(&mut self, index: usize, value: u64) {
+        assert!(index < self.len(), "index out of the vector");
+        match 64 % W {
+            0 => set_aligned_value(&mut self.inner.bytes, W, index, value),
+            _ => set_unaligned_value(&mut self.inner.bytes, W, index, value),
+        }
+    }
+
+    /// Appends a value
+    pub fn push(&mut self, value: u64) {
+        self.inner.len += 1;
+        self.inner.bytes.resize((self.inner.len * W).div_ceil(8), 0);
+        self.set(self.inner.len - 1, value);
+    }
+
+    /// Removes the last value and returns it, `None` if the vector is empty
+    pub fn pop(&mut self) -> Option<u64> {
+        self.inner.pop()
+    }
+
+    /// Shortens the vector to `len` values, has no effect
+    /// if the vector is already shorter
+    pub fn truncate(&mut self, len: usize) {
+        self.inner.truncate(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        self.inner.iter()
+    }
+
+    pub fn as_packed_slice(&self) -> PackedSlice<'_> {
+        self.inner.as_packed_slice()
+    }
+
+    /// View of the values in `range`
+    ///
+    /// **PANIC**: If `range` out of the vector
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> PackedSlice<'_> {
+        self.inner.slice(range)
+    }
+
+    /// Copies all values to a new `Vec`
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.inner.to_vec()
+    }
+
+    /// Converts to the vector with the runtime width
+    pub fn into_dyn(self) -> DynPackedVec {
+        self.inner
+    }
+}
+
+impl<const W: usize> Default for PackedVec<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const W: usize> Extend<u64> for PackedVec<W> {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, const W: usize> IntoIterator for &'a PackedVec<W> {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<const W: usize> fmt::Debug for PackedVec<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests_packed_vec {
+    use super::*;
+
+    #[test]
+    fn check_round_trip() {
+        let values: Vec<u64> = (0..10_000u64).map(|index| index * 7919 % 8192).collect();
+        let vec = PackedVec::<13>::from_slice(&values);
+
+        assert_eq!(vec.len(), 10_000);
+        assert_eq!(vec.as_bytes().len(), (10_000 * 13usize).div_ceil(8));
+        assert_eq!(vec.to_vec(), values);
+        assert_eq!(vec.iter().next_back(), values.last().copied());
+    }
+
+    #[test]
+    fn check_slice() {
+        let vec = PackedVec::<11>::from_slice(&[10, 20, 30, 40, 50]);
+        let slice = vec.slice(1..4);
+        assert_eq!(slice.len(), 3);
+        assert_eq!(slice.get(0), Some(20));
+        assert_eq!(slice.get(3), None);
+        assert_eq!(slice.slice(1..).to_vec(), [30, 40]);
+        assert_eq!(vec.slice(..).to_vec(), vec.to_vec());
+    }
+
+    #[test]
+    fn check_set_get() {
+        let mut vec = PackedVec::<4>::from_slice(&[1, 2, 3]);
+        vec.set(1, 0b11110);
+        assert_eq!(vec.as_bytes(), [0b00011110, 0b00110000]);
+
+        let mut vec = PackedVec::<16>::from_slice(&[1, 2]);
+        vec.set(0, 0xABCD);
+        assert_eq!(vec.as_bytes(), [0xAB, 0xCD, 0x00, 0x02]);
+
+        let mut vec = PackedVec::<13>::from_slice(&[1, 2, 3]);
+        vec.set(1, 8191);
+        assert_eq!(vec.to_vec(), [1, 8191, 3]);
+        assert_eq!(vec.get(3), None);
+    }
+
+    #[test]
+    fn check_into_dyn() {
+        let mut vec = PackedVec::<4>::default();
+        vec.extend([1, 2, 15]);
+        let dyn_vec = vec.into_dyn();
+        assert_eq!(dyn_vec.width(), 4);
+        assert_eq!(dyn_vec.as_bytes(), [0b00010010, 0b11110000]);
+    }
+}
+
+/// View of values of a [`DynPackedVec`] or a [`PackedVec`]
+#[derive(Clone, Copy)]
+pub struct PackedSlice<'a> {
+    bytes: &'a [u8],
+    width: usize,
+    // Index of the first value of the view in the vector
+    start: usize,
+    len: usize,
+}
+
+impl<'a> PackedSlice<'a> {
+    /// Width of the values in bits
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Value at `index` of the view, `None` if out of the view
+    pub fn get(&self, index: usize) -> Option<u64> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(get_value(self.bytes, self.width, self.start + index))
+    }
+
+    /// View of the values in `range` of this view
+    ///
+    /// **PANIC**: If `range` out of the view
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> PackedSlice<'a> {
+        let range = to_range(range, self.len);
+        PackedSlice {
+            bytes: self.bytes,
+            width: self.width,
+            start: self.start + range.start,
+            len: range.len(),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'a> {
+        Iter {
+            bytes: self.bytes,
+            width: self.width,
+            range: self.start..self.start + self.len,
+        }
+    }
+
+    /// Copies all values to a new `Vec`
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for PackedSlice<'a> {
+    type Item = u64;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Debug for PackedSlice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over values of a packed vector
+#[derive(Clone)]
+pub struct Iter<'a> {
+    bytes: &'a [u8],
+    width: usize,
+    range: Range<usize>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.range.next()?;
+        Some(get_value(self.bytes, self.width, index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.range.next_back()?;
+        Some(get_value(self.bytes, self.width, index))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+// Reads the value at `index`
+fn get_value<S>(bytes: &S, width: usize, index: usize) -> u64
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    if 64 % width == 0 {
+        return get_aligned_value(bytes, width, index);
+    }
+    read_bits(bytes, index * width, width)
+}
+
+// Reads the value at `index`, when the width divides 64 a value lies
+// in one byte or takes whole bytes
+fn get_aligned_value<S>(bytes: &S, width: usize, index: usize) -> u64
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    let bit_index = index * width;
+
+    if width < 8 {
+        let shift = 8 - bit_index % 8 - width;
+        let byte = bytes[bit_index / 8] >> shift;
+        return (byte & (0b11111111 >> (8 - width))) as u64;
+    }
+
+    let start_byte_index = bit_index / 8;
+    (0..width / 8).fold(0, |value, byte_index| {
+        (value << 8) | bytes[start_byte_index + byte_index] as u64
+    })
+}
+
+// Replaces the value at `index`, that is inside the bytes
+fn set_value<T>(bytes: &mut T, width: usize, index: usize, value: u64)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    if 64 % width == 0 {
+        set_aligned_value(bytes, width, index, value);
+    } else {
+        set_unaligned_value(bytes, width, index, value);
+    }
+}
+
+// Replaces the value at `index`, when the width divides 64
+fn set_aligned_value<T>(bytes: &mut T, width: usize, index: usize, value: u64)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    let bit_index = index * width;
+
+    if width < 8 {
+        let shift = 8 - bit_index % 8 - width;
+        let mask = (0b11111111 >> (8 - width)) << shift;
+        let byte = &mut bytes[bit_index / 8];
+        *byte = (*byte & !mask) | (((value as u8) << shift) & mask);
+        return;
+    }
+
+    let start_byte_index = bit_index / 8;
+    let source = value.to_be_bytes();
+    for byte_index in 0..width / 8 {
+        bytes[start_byte_index + byte_index] = source[8 - width / 8 + byte_index];
+    }
+}
+
+// Replaces the value at `index` by the general bit routines
+fn set_unaligned_value<T>(bytes: &mut T, width: usize, index: usize, value: u64)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    let bit_index = index * width;
+    let source = value.to_be_bytes();
+    bit_clean(bytes, bit_index, width);
+    bit_write(bytes, bit_index, width, &source, source.len());
+}
@@ -0,0 +1,242 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::logic::bit_fill;
+use crate::{bit_write, is_in_range};
+
+/// What to do with a source value, that does not fit into
+/// the written number of bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Reject the write with [`OutOfRangeError`], the target is not touched
+    Error,
+    /// Discard the most significant bits that are out of the written
+    /// number of bits, the behaviour of [`bit_write`]
+    #[default]
+    Truncate,
+    /// Write the maximum value that the written number of bits can store
+    Saturate,
+    /// Write the value modulo `2^bit_size`
+    ///
+    /// **NOTE**: For unsigned sources it gives the same bits as
+    /// [`OverflowPolicy::Truncate`], the variant states the intent
+    /// of the call site
+    Wrap,
+}
+
+/// Error of a write with [`OverflowPolicy::Error`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfRangeError {
+    /// Offset of the rejected write in the target
+    pub bit_offset: usize,
+    /// Number of bits of the rejected write
+    pub bit_size: usize,
+}
+
+impl fmt::Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: value written at offset {} does not fit into {} bits.",
+            self.bit_offset, self.bit_size
+        )
+    }
+}
+
+impl Error for OutOfRangeError {}
+
+/// Same as [`bit_write`], but a source value that exceeds
+/// `recordable_bit_size` is handled according to `policy`
+///
+/// **PANIC**: If requested bit_size large than source bit size
+pub fn bit_write_with_policy<T, S>(
+    target: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    byte_source_len: usize,
+    policy: OverflowPolicy,
+) -> Result<(), OutOfRangeError>
+where
+    T: IndexMut<usize, Output = u8>,
+    S: Index<usize, Output = u8>,
+{
+    if policy == OverflowPolicy::Truncate || policy == OverflowPolicy::Wrap {
+        bit_write(
+            target,
+            target_bit_offset,
+            recordable_bit_size,
+            source,
+            byte_source_len,
+        );
+        return Ok(());
+    }
+
+    let source_bytes = (0..byte_source_len).map(|index| &source[index]);
+    if is_in_range(recordable_bit_size, source_bytes, byte_source_len) {
+        bit_write(
+            target,
+            target_bit_offset,
+            recordable_bit_size,
+            source,
+            byte_source_len,
+        );
+        return Ok(());
+    }
+
+    if policy == OverflowPolicy::Error {
+        return Err(OutOfRangeError {
+            bit_offset: target_bit_offset,
+            bit_size: recordable_bit_size,
+        });
+    }
+
+    // Saturate, write all ones
+    bit_fill(target, target_bit_offset, recordable_bit_size);
+    Ok(())
+}
+
+/// Writer that applies the same [`OverflowPolicy`] to all its writes
+pub struct PolicyWriter<'t, T> {
+    target: &'t mut T,
+    policy: OverflowPolicy,
+}
+
+impl<'t, T> PolicyWriter<'t, T>
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    pub fn new(target: &'t mut T, policy: OverflowPolicy) -> Self {
+        Self { target, policy }
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Returns the wrapped target
+    pub fn into_inner(self) -> &'t mut T {
+        self.target
+    }
+
+    /// Same as [`bit_write_with_policy`] with the writer policy
+    pub fn bit_write<S>(
+        &mut self,
+        target_bit_offset: usize,
+        recordable_bit_size: usize,
+        source: &S,
+        byte_source_len: usize,
+    ) -> Result<(), OutOfRangeError>
+    where
+        S: Index<usize, Output = u8>,
+    {
+        bit_write_with_policy(
+            self.target,
+            target_bit_offset,
+            recordable_bit_size,
+            source,
+            byte_source_len,
+            self.policy,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_bit_write_with_policy {
+    use super::*;
+
+    #[test]
+    fn check_in_range() {
+        for policy in [
+            OverflowPolicy::Error,
+            OverflowPolicy::Truncate,
+            OverflowPolicy::Saturate,
+            OverflowPolicy::Wrap,
+        ] {
+            let mut target = [0u8; 2];
+            let source = 0b101101u64.to_be_bytes();
+            bit_write_with_policy(&mut target, 3, 6, &source, source.len(), policy).unwrap();
+            assert_eq!(target, [0b00010110, 0b10000000]);
+        }
+    }
+
+    #[test]
+    fn check_error() {
+        let mut target = [0u8; 2];
+        let source = 0b1101101u64.to_be_bytes();
+        let error = bit_write_with_policy(
+            &mut target,
+            3,
+            6,
+            &source,
+            source.len(),
+            OverflowPolicy::Error,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error,
+            OutOfRangeError {
+                bit_offset: 3,
+                bit_size: 6
+            }
+        );
+        assert_eq!(target, [0, 0]);
+    }
+
+    #[test]
+    fn check_truncate_and_wrap() {
+        for policy in [OverflowPolicy::Truncate, OverflowPolicy::Wrap] {
+            let mut target = [0u8; 2];
+            let source = 0b1101101u64.to_be_bytes();
+            bit_write_with_policy(&mut target, 3, 6, &source, source.len(), policy).unwrap();
+            assert_eq!(target, [0b00010110, 0b10000000]);
+        }
+    }
+
+    #[test]
+    fn check_saturate() {
+        let mut target = [0u8; 2];
+        let source = 0b1001101u64.to_be_bytes();
+        bit_write_with_policy(
+            &mut target,
+            3,
+            6,
+            &source,
+            source.len(),
+            OverflowPolicy::Saturate,
+        )
+        .unwrap();
+        assert_eq!(target, [0b00011111, 0b10000000]);
+    }
+
+    #[test]
+    fn check_saturate_wide() {
+        let mut target = [0u8; 12];
+        let source = [0b10000001u8; 12];
+        bit_write_with_policy(
+            &mut target,
+            4,
+            90,
+            &source,
+            source.len(),
+            OverflowPolicy::Saturate,
+        )
+        .unwrap();
+
+        let mut expected = [0b11111111u8; 12];
+        expected[0] = 0b00001111;
+        expected[11] = 0b11111100;
+        assert_eq!(target, expected);
+    }
+
+    #[test]
+    fn check_writer() {
+        let mut target = [0u8; 1];
+        let mut writer = PolicyWriter::new(&mut target, OverflowPolicy::Saturate);
+        writer.bit_write(0, 3, &[0b00001000u8], 1).unwrap();
+        writer.bit_write(3, 3, &[0b00000010u8], 1).unwrap();
+        assert_eq!(target, [0b11101000]);
+    }
+}
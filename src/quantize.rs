@@ -0,0 +1,394 @@
+//! Floats stored as integers of N bits mapped onto a `[min, max]` range
+//!
+//! ```text
+//! code:  0     1     2    ...   2^N - 1
+//!        |-----|-----|--- ... ---|
+//! value: min  min+step           max      step = (max - min) / (2^N - 1)
+//! ```
+//!
+//! Both ends of the range are stored exactly, the error of other values
+//! depends on the rounding, see [`Quantizer::max_error`]
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::{bit_write, read_bits};
+
+/// The widest code, codes up to `2^53` are exact in `f64`
+pub const MAX_BIT_SIZE: usize = 53;
+
+/// How a value between two codes is mapped to a code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// The closest code, the error is up to a half of the step
+    #[default]
+    Nearest,
+    /// The code below the value, the read value never exceeds the written
+    Floor,
+    /// The code above the value, the read value is never less than the written
+    Ceil,
+}
+
+/// Error of a value out of the range of a [`Quantizer`] without clamping
+/// or of NaN
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizeError {
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl fmt::Display for QuantizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: value {} is out of range [{}, {}].",
+            self.value, self.min, self.max
+        )
+    }
+}
+
+impl Error for QuantizeError {}
+
+/// Mapping of floats of `[min, max]` to codes of `bit_size` bits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantizer {
+    bit_size: usize,
+    min: f64,
+    max: f64,
+    rounding: Rounding,
+    clamp: bool,
+}
+
+impl Quantizer {
+    /// Quantizer with [`Rounding::Nearest`] and without clamping
+    ///
+    /// **PANIC**: If `bit_size` is zero or large than [`MAX_BIT_SIZE`],
+    /// or if `min` and `max` are not finite or `min >= max`
+    pub fn new(bit_size: usize, min: f64, max: f64) -> Self {
+        assert!(
+            (1..=MAX_BIT_SIZE).contains(&bit_size),
+            "bit_size out of 1..=53"
+        );
+        assert!(
+            min.is_finite() && max.is_finite() && min < max,
+            "wrong range"
+        );
+        Self {
+            bit_size,
+            min,
+            max,
+            rounding: Rounding::default(),
+            clamp: false,
+        }
+    }
+
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// With clamping values out of the range are written as the nearest
+    /// end of the range instead of [`QuantizeError`]
+    pub fn with_clamp(mut self, clamp: bool) -> Self {
+        self.clamp = clamp;
+        self
+    }
+
+    pub fn bit_size(&self) -> usize {
+        self.bit_size
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// Difference of values of neighbouring codes
+    pub fn step(&self) -> f64 {
+        step(self.min, self.max, self.bit_size)
+    }
+
+    /// The largest difference of a written value of the range
+    /// and the read one, up to rounding of floats
+    pub fn max_error(&self) -> f64 {
+        max_error(self.step(), self.rounding)
+    }
+
+    /// Code of `value`
+    pub fn quantize(&self, value: f64) -> Result<u64, QuantizeError> {
+        let error = QuantizeError {
+            value,
+            min: self.min,
+            max: self.max,
+        };
+        if value.is_nan() {
+            return Err(error);
+        }
+        let value = match self.clamp {
+            true => value.clamp(self.min, self.max),
+            false if value < self.min || value > self.max => return Err(error),
+            false => value,
+        };
+
+        // The division can give a position off by one code, the code
+        // is corrected by the values of its neighbours
+        let max_code = max_code(self.bit_size);
+        let position = (value - self.min) / self.step();
+        let code = (position.round() as u64).min(max_code);
+        let below = code.saturating_sub(1);
+        let above = (code + 1).min(max_code);
+        let code = match self.rounding {
+            Rounding::Nearest => [below, code, above]
+                .into_iter()
+                .min_by(|a, b| {
+                    let a = (self.dequantize(*a) - value).abs();
+                    let b = (self.dequantize(*b) - value).abs();
+                    a.total_cmp(&b)
+                })
+                .unwrap(),
+            Rounding::Floor => [above, code, below]
+                .into_iter()
+                .find(|code| self.dequantize(*code) <= value)
+                .unwrap_or(0),
+            Rounding::Ceil => [below, code, above]
+                .into_iter()
+                .find(|code| self.dequantize(*code) >= value)
+                .unwrap_or(max_code),
+        };
+        Ok(code)
+    }
+
+    /// Value of `code`, the ends of the range are exact
+    ///
+    /// **NOTE**: Bits of `code`, that are out of `bit_size`, are discarded
+    pub fn dequantize(&self, code: u64) -> f64 {
+        let max_code = max_code(self.bit_size);
+        match code & max_code {
+            code if code == max_code => self.max,
+            code => self.min + code as f64 * self.step(),
+        }
+    }
+
+    /// Writes the code of `value` to target by bit offset
+    ///
+    /// **PANIC**: If the target is too small
+    ///
+    /// **NOTE**: The code is ORed into the target as by [`bit_write`],
+    /// so the field is expected to be clean
+    pub fn write<T>(
+        &self,
+        target: &mut T,
+        target_bit_offset: usize,
+        value: f64,
+    ) -> Result<(), QuantizeError>
+    where
+        T: IndexMut<usize, Output = u8>,
+    {
+        let code = self.quantize(value)?;
+        bit_write(
+            target,
+            target_bit_offset,
+            self.bit_size,
+            &code.to_be_bytes(),
+            8,
+        );
+        Ok(())
+    }
+
+    /// Reads a value written by [`write`](Self::write) from source
+    /// by bit offset
+    ///
+    /// **PANIC**: If the source is too small
+    pub fn read<S>(&self, source: &S, source_bit_offset: usize) -> f64
+    where
+        S: Index<usize, Output = u8>,
+    {
+        self.dequantize(read_bits(source, source_bit_offset, self.bit_size))
+    }
+}
+
+#[cfg(test)]
+mod tests_quantizer {
+    use super::*;
+
+    #[test]
+    fn check_codes() {
+        // Step 0.5
+        let quantizer = Quantizer::new(3, -1.0, 2.5);
+        assert_eq!(quantizer.step(), 0.5);
+        assert_eq!(quantizer.quantize(-1.0), Ok(0));
+        assert_eq!(quantizer.quantize(2.5), Ok(7));
+        assert_eq!(quantizer.quantize(0.7), Ok(3));
+        assert_eq!(
+            quantizer.with_rounding(Rounding::Floor).quantize(0.9),
+            Ok(3)
+        );
+        assert_eq!(quantizer.with_rounding(Rounding::Ceil).quantize(0.1), Ok(3));
+        assert_eq!(quantizer.dequantize(3), 0.5);
+        assert_eq!(quantizer.dequantize(7), 2.5);
+    }
+
+    #[test]
+    fn check_range() {
+        let quantizer = Quantizer::new(8, 0.0, 1.0);
+        assert_eq!(
+            quantizer.quantize(1.5),
+            Err(QuantizeError {
+                value: 1.5,
+                min: 0.0,
+                max: 1.0
+            })
+        );
+        assert!(quantizer.quantize(f64::NAN).is_err());
+
+        let quantizer = quantizer.with_clamp(true);
+        assert_eq!(quantizer.quantize(1.5), Ok(255));
+        assert_eq!(quantizer.quantize(f64::NEG_INFINITY), Ok(0));
+        assert!(quantizer.quantize(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn check_error_bounds() {
+        for rounding in [Rounding::Nearest, Rounding::Floor, Rounding::Ceil] {
+            let quantizer = Quantizer::new(10, -180.0, 180.0).with_rounding(rounding);
+            for index in 0..=3600 {
+                let value = -180.0 + index as f64 * 0.1;
+                let read = quantizer.dequantize(quantizer.quantize(value).unwrap());
+                assert!((read - value).abs() <= quantizer.max_error() * (1.0 + 1e-9));
+                match rounding {
+                    Rounding::Floor => assert!(read <= value),
+                    Rounding::Ceil => assert!(read >= value),
+                    Rounding::Nearest => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_write_read() {
+        let quantizer = Quantizer::new(12, 0.0, 100.0);
+        let mut target = vec![0b11000000u8, 0, 0];
+        quantizer.write(&mut target, 2, 42.0).unwrap();
+        assert_eq!(target[0] >> 6, 0b11);
+        assert!((quantizer.read(&target, 2) - 42.0).abs() <= quantizer.max_error());
+        assert_eq!(quantizer.read(&[0xFFu8, 0xFF], 0), 100.0);
+    }
+}
+
+/// Writes `value` of `[min, max]` as a code of `bit_size` bits with
+/// [`Rounding::Nearest`], see [`Quantizer`] for other options
+///
+/// **PANIC**: See [`Quantizer::new`] and [`Quantizer::write`]
+///
+/// **NOTE**: The same as for [`Quantizer::write`]
+pub fn write_quantized<T>(
+    target: &mut T,
+    target_bit_offset: usize,
+    bit_size: usize,
+    value: f64,
+    min: f64,
+    max: f64,
+) -> Result<(), QuantizeError>
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    Quantizer::new(bit_size, min, max).write(target, target_bit_offset, value)
+}
+
+/// Reads a value written by [`write_quantized`]
+///
+/// **PANIC**: See [`Quantizer::new`] and [`Quantizer::read`]
+pub fn read_quantized<S>(
+    source: &S,
+    source_bit_offset: usize,
+    bit_size: usize,
+    min: f64,
+    max: f64,
+) -> f64
+where
+    S: Index<usize, Output = u8>,
+{
+    Quantizer::new(bit_size, min, max).read(source, source_bit_offset)
+}
+
+#[cfg(test)]
+mod tests_write_quantized {
+    use super::*;
+
+    #[test]
+    fn check_fields() {
+        // Position and heading in 10 + 10 + 6 bits
+        let mut target = vec![0u8; 4];
+        write_quantized(&mut target, 0, 10, 12.34, -50.0, 50.0).unwrap();
+        write_quantized(&mut target, 10, 10, -7.5, -50.0, 50.0).unwrap();
+        write_quantized(&mut target, 20, 6, 270.0, 0.0, 360.0).unwrap();
+
+        assert!((read_quantized(&target, 0, 10, -50.0, 50.0) - 12.34).abs() < 0.05);
+        assert!((read_quantized(&target, 10, 10, -50.0, 50.0) + 7.5).abs() < 0.05);
+        assert!((read_quantized(&target, 20, 6, 0.0, 360.0) - 270.0).abs() < 2.9);
+    }
+}
+
+/// The smallest number of bits, whose [`Quantizer::max_error`] for
+/// the range and rounding does not exceed `precision`, `None` if
+/// [`MAX_BIT_SIZE`] bits are not enough
+///
+/// **PANIC**: If `min` and `max` are not finite or `min >= max`
+pub fn bits_for_precision(min: f64, max: f64, precision: f64, rounding: Rounding) -> Option<usize> {
+    assert!(
+        min.is_finite() && max.is_finite() && min < max,
+        "wrong range"
+    );
+    (1..=MAX_BIT_SIZE).find(|bit_size| max_error(step(min, max, *bit_size), rounding) <= precision)
+}
+
+#[cfg(test)]
+mod tests_bits_for_precision {
+    use super::*;
+
+    #[test]
+    fn check_bits() {
+        // Centimetres over 1 km: 1000 / 0.02 = 50000 steps
+        assert_eq!(
+            bits_for_precision(0.0, 1000.0, 0.01, Rounding::Nearest),
+            Some(16)
+        );
+        assert_eq!(
+            bits_for_precision(0.0, 1000.0, 0.01, Rounding::Floor),
+            Some(17)
+        );
+        assert_eq!(
+            bits_for_precision(0.0, 1.0, 0.5, Rounding::Nearest),
+            Some(1)
+        );
+        assert_eq!(bits_for_precision(0.0, 1.0, 0.0, Rounding::Nearest), None);
+
+        let bit_size = bits_for_precision(-90.0, 90.0, 1e-5, Rounding::Nearest).unwrap();
+        assert!(Quantizer::new(bit_size, -90.0, 90.0).max_error() <= 1e-5);
+        assert!(Quantizer::new(bit_size - 1, -90.0, 90.0).max_error() > 1e-5);
+    }
+}
+
+fn max_code(bit_size: usize) -> u64 {
+    (1 << bit_size) - 1
+}
+
+fn step(min: f64, max: f64, bit_size: usize) -> f64 {
+    (max - min) / max_code(bit_size) as f64
+}
+
+fn max_error(step: f64, rounding: Rounding) -> f64 {
+    match rounding {
+        Rounding::Nearest => step / 2.0,
+        Rounding::Floor | Rounding::Ceil => step,
+    }
+}
@@ -0,0 +1,306 @@
+//! Mixed-radix packing of bounded values
+//!
+//! Values of ranges, that are not powers of two, are combined into one
+//! number `v0 + r0 * (v1 + r1 * (v2 + ...))`, that takes
+//! `ceil(log2(r0 * r1 * ...))` bits instead of the sum of widths
+//! of every range
+//!
+//! ```text
+//! radices:  6, 100, 3
+//! separate: 3 + 7 + 2 = 12 bits
+//! combined: ceil(log2(1800)) = 11 bits
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::{bit_read, bit_write};
+
+/// Error of a value, that is not less than the radix of its position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfRadixError {
+    /// Position of the value
+    pub index: usize,
+    pub value: u64,
+    pub radix: u64,
+}
+
+impl fmt::Display for OutOfRadixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: value {} at {} is out of radix {}.",
+            self.value, self.index, self.radix
+        )
+    }
+}
+
+impl Error for OutOfRadixError {}
+
+#[cfg(test)]
+mod tests_out_of_radix_error {
+    use super::*;
+
+    #[test]
+    fn check_display() {
+        let error = OutOfRadixError {
+            index: 2,
+            value: 7,
+            radix: 3,
+        };
+        assert_eq!(error.to_string(), "Error: value 7 at 2 is out of radix 3.");
+    }
+}
+
+/// Packer of values of `0..radix` for every radix into the minimal
+/// number of bits. The first value is the least significant digit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixedRadix {
+    radices: Vec<u64>,
+    bit_len: usize,
+    // Number of 64 bit limbs of the combined number
+    limb_len: usize,
+}
+
+impl MixedRadix {
+    /// **PANIC**: If a radix is zero
+    pub fn new(radices: &[u64]) -> Self {
+        assert!(radices.iter().all(|radix| *radix != 0), "zero radix");
+
+        // The largest combined number is the product minus one
+        let mut max = vec![1];
+        for radix in radices {
+            mul_add(&mut max, *radix, 0);
+        }
+        sub_one(&mut max);
+        let bit_len = match max.iter().rposition(|limb| *limb != 0) {
+            Some(index) => index * 64 + (64 - max[index].leading_zeros() as usize),
+            None => 0,
+        };
+
+        Self {
+            radices: radices.to_vec(),
+            bit_len,
+            limb_len: bit_len.div_ceil(64).max(1),
+        }
+    }
+
+    pub fn radices(&self) -> &[u64] {
+        &self.radices
+    }
+
+    /// Number of bits of packed values
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Writes `values` as one number of [`bit_len`](Self::bit_len) bits
+    /// to target by bit offset
+    ///
+    /// **PANIC**: If the number of values differs from the number
+    /// of radices, or if the target is too small
+    ///
+    /// **NOTE**: The number is ORed into the target as by [`bit_write`],
+    /// so the field is expected to be clean
+    pub fn write<T>(
+        &self,
+        values: &[u64],
+        target: &mut T,
+        target_bit_offset: usize,
+    ) -> Result<(), OutOfRadixError>
+    where
+        T: IndexMut<usize, Output = u8>,
+    {
+        assert_eq!(values.len(), self.radices.len(), "wrong number of values");
+        if let Some(index) = (0..values.len()).find(|index| values[*index] >= self.radices[*index])
+        {
+            return Err(OutOfRadixError {
+                index,
+                value: values[index],
+                radix: self.radices[index],
+            });
+        }
+
+        let mut number = vec![0; self.limb_len];
+        for (value, radix) in values.iter().zip(&self.radices).rev() {
+            mul_add(&mut number, *radix, *value);
+        }
+        let source = to_be_bytes(&number);
+        bit_write(
+            target,
+            target_bit_offset,
+            self.bit_len,
+            &source,
+            source.len(),
+        );
+        Ok(())
+    }
+
+    /// Reads values written by [`write`](Self::write) from source
+    /// by bit offset
+    ///
+    /// **PANIC**: If the source is too small
+    ///
+    /// **NOTE**: Bits, that were not written by [`write`](Self::write), can
+    /// give a number out of the radices, then the last value is out of its
+    /// radix (saturated to `u64::MAX` if it does not fit)
+    pub fn read<S>(&self, source: &S, source_bit_offset: usize) -> Result<Vec<u64>, OutOfRadixError>
+    where
+        S: Index<usize, Output = u8>,
+    {
+        let mut bytes = vec![0u8; self.limb_len * 8];
+        let byte_len = bytes.len();
+        bit_read(
+            source,
+            source_bit_offset,
+            self.bit_len,
+            &mut bytes,
+            byte_len,
+        );
+
+        let mut number = from_be_bytes(&bytes);
+        let Some((last_radix, radices)) = self.radices.split_last() else {
+            return Ok(vec![]);
+        };
+        let mut values: Vec<u64> = radices
+            .iter()
+            .map(|radix| div_rem(&mut number, *radix))
+            .collect();
+
+        // The rest of the number is the last value
+        let last = match number[1..].iter().all(|limb| *limb == 0) {
+            true => number[0],
+            false => u64::MAX,
+        };
+        if last >= *last_radix {
+            return Err(OutOfRadixError {
+                index: radices.len(),
+                value: last,
+                radix: *last_radix,
+            });
+        }
+        values.push(last);
+        Ok(values)
+    }
+
+    /// Packs `values` to `bit_len().div_ceil(8)` bytes from the most
+    /// significant bit, the padding bits are zeros
+    ///
+    /// **PANIC**: If the number of values differs from the number
+    /// of radices
+    pub fn pack(&self, values: &[u64]) -> Result<Vec<u8>, OutOfRadixError> {
+        let mut bytes = vec![0; self.bit_len.div_ceil(8)];
+        self.write(values, &mut bytes, 0)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests_mixed_radix {
+    use super::*;
+
+    #[test]
+    fn check_bit_len() {
+        assert_eq!(MixedRadix::new(&[6, 100, 3]).bit_len(), 11);
+        assert_eq!(MixedRadix::new(&[2, 2, 2]).bit_len(), 3);
+        assert_eq!(MixedRadix::new(&[1, 1]).bit_len(), 0);
+        assert_eq!(MixedRadix::new(&[]).bit_len(), 0);
+        assert_eq!(MixedRadix::new(&[u64::MAX, 2]).bit_len(), 65);
+        // 10^30 < 2^100
+        assert_eq!(MixedRadix::new(&[10; 30]).bit_len(), 100);
+    }
+
+    #[test]
+    fn check_pack() {
+        let radix = MixedRadix::new(&[6, 100, 3]);
+        // 5 + 6 * (99 + 100 * 2) = 1799
+        let bytes = radix.pack(&[5, 99, 2]).unwrap();
+        assert_eq!(bytes, [0b11100000, 0b11100000]);
+        assert_eq!(radix.read(&bytes, 0), Ok(vec![5, 99, 2]));
+
+        assert_eq!(
+            radix.pack(&[5, 100, 2]),
+            Err(OutOfRadixError {
+                index: 1,
+                value: 100,
+                radix: 100
+            })
+        );
+    }
+
+    #[test]
+    fn check_offset() {
+        let radix = MixedRadix::new(&[3, 5, 7, 1_000_000_007, 11, 13]);
+        let values = [2, 0, 6, 123_456_789, 10, 7];
+        let mut target = vec![0u8; 8];
+        target[0] = 0b10100000;
+
+        radix.write(&values, &mut target, 3).unwrap();
+        assert_eq!(target[0] >> 5, 0b101);
+        assert_eq!(radix.read(&target, 3), Ok(values.to_vec()));
+    }
+
+    #[test]
+    fn check_wide() {
+        let radices: Vec<u64> = (0..40u64).map(|index| 1000 + index * 7919).collect();
+        let values: Vec<u64> = radices.iter().map(|radix| radix - 1 - radix % 17).collect();
+        let radix = MixedRadix::new(&radices);
+        let bytes = radix.pack(&values).unwrap();
+        assert_eq!(bytes.len(), radix.bit_len().div_ceil(8));
+        assert_eq!(radix.read(&bytes, 0), Ok(values));
+
+        // All ones are out of the radices
+        let ones = vec![0xFFu8; bytes.len()];
+        assert_eq!(radix.read(&ones, 0).unwrap_err().index, 39);
+    }
+}
+
+// number = number * multiplier + addend, the number is of little-endian limbs
+fn mul_add(number: &mut Vec<u64>, multiplier: u64, addend: u64) {
+    let mut carry = addend as u128;
+    for limb in number.iter_mut() {
+        let product = *limb as u128 * multiplier as u128 + carry;
+        *limb = product as u64;
+        carry = product >> 64;
+    }
+    if carry != 0 {
+        number.push(carry as u64);
+    }
+}
+
+fn sub_one(number: &mut [u64]) {
+    for limb in number.iter_mut() {
+        let (difference, borrow) = limb.overflowing_sub(1);
+        *limb = difference;
+        if !borrow {
+            return;
+        }
+    }
+}
+
+// number = number / divisor, returns the remainder
+fn div_rem(number: &mut [u64], divisor: u64) -> u64 {
+    let mut remainder = 0u128;
+    for limb in number.iter_mut().rev() {
+        let dividend = (remainder << 64) | *limb as u128;
+        *limb = (dividend / divisor as u128) as u64;
+        remainder = dividend % divisor as u128;
+    }
+    remainder as u64
+}
+
+fn to_be_bytes(number: &[u64]) -> Vec<u8> {
+    number
+        .iter()
+        .rev()
+        .flat_map(|limb| limb.to_be_bytes())
+        .collect()
+}
+
+fn from_be_bytes(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .rchunks(8)
+        .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
@@ -0,0 +1,164 @@
+use std::ops::{Index, IndexMut};
+
+use crate::{bit_clean, membitcpy};
+
+// Every byte with the reversed order of bits
+pub(crate) const REVERSED: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut bit = 0;
+        while bit < 8 {
+            if byte & (1 << bit) != 0 {
+                table[byte] |= 0b10000000 >> bit;
+            }
+            bit += 1;
+        }
+        byte += 1;
+    }
+    table
+};
+
+/// Reverses the order of `bit_size` bits of target at `bit_offset` in place,
+/// that is the first bit of the range becomes the last one
+///
+/// **PANIC**: If out of range target
+pub fn reverse_bits_range<T>(target: &mut T, bit_offset: usize, bit_size: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    let (reversed, reversed_bit_offset) = reversed(target, bit_offset, bit_size);
+    bit_clean(target, bit_offset, bit_size);
+    membitcpy(target, bit_offset, bit_size, &reversed, reversed_bit_offset);
+}
+
+#[cfg(test)]
+mod tests_reverse_bits_range {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_unaligned() {
+        let mut target = [0b11100110u8, 0b10111111u8];
+        reverse_bits_range(&mut target, 3, 10);
+        assert_eq!(target, [0b11111101, 0b01100111]);
+    }
+
+    #[test]
+    fn check_aligned() {
+        let mut target = [0b00000001u8, 0b10000000u8, 0b11110000u8];
+        reverse_bits_range(&mut target, 0, 24);
+        assert_eq!(target, [0b00001111, 0b00000001, 0b10000000]);
+    }
+
+    #[test]
+    fn check_twice() {
+        let origin = test_bytes(20, 0);
+        let mut target = origin.clone();
+        reverse_bits_range(&mut target, 7, 139);
+        assert_ne!(target, origin);
+        reverse_bits_range(&mut target, 7, 139);
+        assert_eq!(target, origin);
+    }
+}
+
+/// Same as [`membitcpy`], but the bits of the source are written to the
+/// target in reverse order, that is the bit at `source_bit_offset`
+/// becomes the last written bit
+///
+/// **PANIC**: If out of range target or source
+///
+/// **NOTE**: The bits are ORed into the target as by [`membitcpy`]
+pub fn membitcpy_reflected<T, S>(
+    target: &mut T,
+    target_bit_offset: usize,
+    recordable_bit_size: usize,
+    source: &S,
+    source_bit_offset: usize,
+) where
+    T: IndexMut<usize, Output = u8>,
+    S: Index<usize, Output = u8>,
+{
+    let (reversed, reversed_bit_offset) = reversed(source, source_bit_offset, recordable_bit_size);
+    membitcpy(
+        target,
+        target_bit_offset,
+        recordable_bit_size,
+        &reversed,
+        reversed_bit_offset,
+    );
+}
+
+#[cfg(test)]
+mod tests_membitcpy_reflected {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_unaligned() {
+        let mut target = [0u8; 2];
+        let source = [0b00011010u8, 0b01000000u8];
+        membitcpy_reflected(&mut target, 6, 7, &source, 3);
+        assert_eq!(target, [0b00000010, 0b01011000]);
+    }
+
+    #[test]
+    fn check_against_reverse() {
+        let source = test_bytes(20, 0);
+
+        let mut target = vec![0u8; 20];
+        membitcpy_reflected(&mut target, 11, 130, &source, 2);
+
+        let mut expected = vec![0u8; 20];
+        membitcpy(&mut expected, 11, 130, &source, 2);
+        reverse_bits_range(&mut expected, 11, 130);
+        assert_eq!(target, expected);
+    }
+}
+
+/// Reverses the order of `width` least significant bits of `value`, for
+/// example to reflect a CRC register, the other bits are discarded
+///
+/// **PANIC**: If `width` large than 64
+pub fn reflect_bits(value: u64, width: usize) -> u64 {
+    assert!(width <= 64, "width large than 64");
+
+    let bytes = value.to_le_bytes().map(|byte| REVERSED[byte as usize]);
+    u64::from_be_bytes(bytes)
+        .checked_shr(64 - width as u32)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests_reflect_bits {
+    use super::*;
+
+    #[test]
+    fn check_widths() {
+        assert_eq!(reflect_bits(0b1101, 4), 0b1011);
+        assert_eq!(reflect_bits(0b1111_0001, 5), 0b10001);
+        assert_eq!(reflect_bits(0x04C1_1DB7, 32), 0xEDB8_8320);
+        assert_eq!(reflect_bits(1, 64), 1 << 63);
+        assert_eq!(reflect_bits(u64::MAX, 0), 0);
+    }
+}
+
+// Copies `bit_size` bits of source at `bit_offset` in reverse order
+// to a new buffer, returns it and the offset of the bits in it
+fn reversed<S>(source: &S, bit_offset: usize, bit_size: usize) -> (Vec<u8>, usize)
+where
+    S: Index<usize, Output = u8>,
+{
+    let mut copy = vec![0u8; bit_size.div_ceil(8)];
+    membitcpy(&mut copy, 0, bit_size, source, bit_offset);
+
+    // Reversing the whole bytes moves the unused bits of
+    // the last byte to the start of the buffer
+    let reversed: Vec<u8> = copy
+        .iter()
+        .rev()
+        .map(|byte| REVERSED[*byte as usize])
+        .collect();
+    let unused = reversed.len() * 8 - bit_size;
+    (reversed, unused)
+}
@@ -0,0 +1,276 @@
+use std::ops::Index;
+
+/// Number of set bits among `bit_len` bits of source at `bit_offset`
+///
+/// **PANIC**: If out of range source
+pub fn count_ones<S>(source: &S, bit_offset: usize, bit_len: usize) -> usize
+where
+    S: Index<usize, Output = u8>,
+{
+    let end = bit_offset + bit_len;
+
+    let mut count = 0;
+    let mut from = bit_offset;
+    while from < end {
+        let to = forward_chunk_end(from, end);
+        count += word(source, from, to).count_ones() as usize;
+        from = to;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests_count_ones {
+    use super::*;
+
+    #[test]
+    fn check_edges() {
+        let source = [0b11110000u8, 0b11111111u8, 0b00001111u8];
+        assert_eq!(count_ones(&source, 2, 20), 12);
+        assert_eq!(count_ones(&source, 5, 2), 0);
+        assert_eq!(count_ones(&source, 0, 0), 0);
+    }
+
+    #[test]
+    fn check_long() {
+        let mut source = [0b10101010u8; 30];
+        source[17] = 0b11111111;
+        assert_eq!(count_ones(&source, 3, 230), 115 + 4);
+    }
+}
+
+/// Number of unset bits among `bit_len` bits of source at `bit_offset`
+///
+/// **PANIC**: If out of range source
+pub fn count_zeros<S>(source: &S, bit_offset: usize, bit_len: usize) -> usize
+where
+    S: Index<usize, Output = u8>,
+{
+    bit_len - count_ones(source, bit_offset, bit_len)
+}
+
+#[cfg(test)]
+mod tests_count_zeros {
+    use super::*;
+
+    #[test]
+    fn check_edges() {
+        let source = [0b11110000u8, 0b11111111u8, 0b00001111u8];
+        assert_eq!(count_zeros(&source, 2, 20), 8);
+        assert_eq!(count_zeros(&source, 4, 4), 4);
+    }
+}
+
+/// Number of unset bits at the start of the range (its most significant
+/// bits), `bit_len` if there is no set bit
+///
+/// **PANIC**: If out of range source
+pub fn leading_zeros<S>(source: &S, bit_offset: usize, bit_len: usize) -> usize
+where
+    S: Index<usize, Output = u8>,
+{
+    find_first_one(source, bit_offset, bit_len).unwrap_or(bit_len)
+}
+
+#[cfg(test)]
+mod tests_leading_zeros {
+    use super::*;
+
+    #[test]
+    fn check_prefix() {
+        // Exp-Golomb prefix of the code `0001010`
+        let source = [0b00000000u8, 0b00010100u8];
+        assert_eq!(leading_zeros(&source, 8, 8), 3);
+        assert_eq!(leading_zeros(&source, 3, 13), 8);
+        assert_eq!(leading_zeros(&source, 0, 11), 11);
+    }
+
+    #[test]
+    fn check_long() {
+        let mut source = [0u8; 40];
+        source[33] = 0b00000100;
+        assert_eq!(leading_zeros(&source, 7, 300), 33 * 8 + 5 - 7);
+    }
+}
+
+/// Number of unset bits at the end of the range (its least significant
+/// bits), `bit_len` if there is no set bit
+///
+/// **PANIC**: If out of range source
+pub fn trailing_zeros<S>(source: &S, bit_offset: usize, bit_len: usize) -> usize
+where
+    S: Index<usize, Output = u8>,
+{
+    let end = bit_offset + bit_len;
+
+    // Go from the end, the last chunk is the first to be checked
+    let mut to = end;
+    while to > bit_offset {
+        let from = backward_chunk_start(bit_offset, to);
+        let word = word(source, from, to);
+        if word != 0 {
+            let ahead_empty_bit = 64 - (to - from);
+            return end - to + word.trailing_zeros() as usize - ahead_empty_bit;
+        }
+        to = from;
+    }
+    bit_len
+}
+
+#[cfg(test)]
+mod tests_trailing_zeros {
+    use super::*;
+
+    #[test]
+    fn check_edges() {
+        let source = [0b00100000u8, 0b00000000u8, 0b10000000u8];
+        assert_eq!(trailing_zeros(&source, 0, 12), 9);
+        assert_eq!(trailing_zeros(&source, 1, 16), 0);
+        assert_eq!(trailing_zeros(&source, 3, 5), 5);
+    }
+
+    #[test]
+    fn check_long() {
+        let mut source = [0u8; 40];
+        source[2] = 0b00010000;
+        assert_eq!(trailing_zeros(&source, 19, 300), 300 - 1);
+        assert_eq!(trailing_zeros(&source, 5, 310), 310 - (19 - 5) - 1);
+    }
+}
+
+/// Index (counting from `bit_offset`) of the first set bit of the range,
+/// `None` if there is no set bit
+///
+/// **PANIC**: If out of range source
+pub fn find_first_one<S>(source: &S, bit_offset: usize, bit_len: usize) -> Option<usize>
+where
+    S: Index<usize, Output = u8>,
+{
+    find(source, bit_offset, bit_len, 0, true)
+}
+
+#[cfg(test)]
+mod tests_find_first_one {
+    use super::*;
+
+    #[test]
+    fn check_find() {
+        let source = [0b00000000u8, 0b00000001u8, 0b11111111u8];
+        assert_eq!(find_first_one(&source, 2, 20), Some(13));
+        assert_eq!(find_first_one(&source, 2, 13), None);
+        assert_eq!(find_first_one(&source, 15, 9), Some(0));
+    }
+}
+
+/// Index (counting from `bit_offset`) of the first unset bit of the range
+/// at `from` or after it, `None` if there is no such bit
+///
+/// **PANIC**: If out of range source
+pub fn find_next_zero<S>(
+    source: &S,
+    bit_offset: usize,
+    bit_len: usize,
+    from: usize,
+) -> Option<usize>
+where
+    S: Index<usize, Output = u8>,
+{
+    find(source, bit_offset, bit_len, from, false)
+}
+
+#[cfg(test)]
+mod tests_find_next_zero {
+    use super::*;
+
+    #[test]
+    fn check_allocation_map() {
+        let source = [0b11111111u8, 0b11101111u8, 0b11111111u8, 0b11111110u8];
+        assert_eq!(find_next_zero(&source, 0, 32, 0), Some(11));
+        assert_eq!(find_next_zero(&source, 0, 32, 11), Some(11));
+        assert_eq!(find_next_zero(&source, 0, 32, 12), Some(31));
+        assert_eq!(find_next_zero(&source, 0, 31, 12), None);
+        assert_eq!(find_next_zero(&source, 4, 20, 25), None);
+    }
+
+    #[test]
+    fn check_long() {
+        let mut source = [0b11111111u8; 40];
+        source[35] = 0b11111101;
+        assert_eq!(find_next_zero(&source, 3, 310, 1), Some(35 * 8 + 6 - 3));
+    }
+}
+
+// Index of the first bit equal to `one` at `from` or after it
+fn find<S>(source: &S, bit_offset: usize, bit_len: usize, from: usize, one: bool) -> Option<usize>
+where
+    S: Index<usize, Output = u8>,
+{
+    let end = bit_offset + bit_len;
+
+    let mut from = bit_offset + from;
+    while from < end {
+        let to = forward_chunk_end(from, end);
+        let mut word = word(source, from, to);
+        if !one {
+            // Invert only the bits of the chunk, they are the most significant
+            word = !word & !u64::MAX.checked_shr((to - from) as u32).unwrap_or_default();
+        }
+        if word != 0 {
+            return Some(from - bit_offset + word.leading_zeros() as usize);
+        }
+        from = to;
+    }
+    None
+}
+
+// End of the chunk that starts at `from`: the rest of the byte if `from`
+// is not byte aligned, otherwise up to a whole word
+fn forward_chunk_end(from: usize, end: usize) -> usize {
+    if !from.is_multiple_of(8) {
+        return (from + 8 - from % 8).min(end);
+    }
+    (from + 64).min(end)
+}
+
+// Start of the chunk that ends at `to`, the chunk is either byte aligned
+// at the start or lies in one byte
+fn backward_chunk_start(start: usize, to: usize) -> usize {
+    let from = to
+        .saturating_sub(64)
+        .next_multiple_of(8)
+        .max(start.next_multiple_of(8));
+    if from >= to {
+        return start.max(to - (to - 1) % 8 - 1);
+    }
+    from
+}
+
+// Bits `from..to` of the source (up to 64) as the most significant bits
+// of a word, the rest is zero. Either `from` is byte aligned or the bits
+// lie in one byte
+fn word<S>(source: &S, from: usize, to: usize) -> u64
+where
+    S: Index<usize, Output = u8>,
+{
+    let start_byte_index = from / 8;
+    let end_byte_index = to.div_ceil(8);
+
+    let mut word = 0u64;
+    for index in start_byte_index..end_byte_index {
+        word = (word << 8) | source[index] as u64;
+    }
+    let bytes_num = (end_byte_index - start_byte_index) as u32;
+    word = word.checked_shl(64 - bytes_num * 8).unwrap_or_default();
+
+    // Mask the edge bits the same way as `bit_clean`, for example
+    // `from % 8` is 2 and `to - from` is 3, then
+    //
+    // |0|0|1|1|1|0|0|0|...
+    //      -----
+    let ahead = from % 8;
+    let mask = (u64::MAX >> ahead)
+        & !u64::MAX
+            .checked_shr((ahead + to - from) as u32)
+            .unwrap_or_default();
+    (word & mask) << ahead
+}
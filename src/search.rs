@@ -0,0 +1,344 @@
+use crate::logic::bit_fill;
+use crate::membitcpy;
+use crate::slice::get_bit;
+
+/// Bit pattern prepared for searching in a bitstream, for example a sync
+/// word of frames, that can start at any bit
+///
+/// Exact matches are searched with Horspool skip tables. The pattern is
+/// shifted to all 8 bit positions in a byte once, and every shift has its
+/// own byte aligned window with a table of skips by the last byte of the
+/// window. The windows move in the order of the bits they start at, by up
+/// to the byte length of the pattern, so a search usually reads a fraction
+/// of the haystack. In the worst case (periodic haystacks) it is `O(n * m)`
+/// byte comparisons for `n` haystack bytes and `m` pattern bytes
+///
+/// Matches with bit errors are searched with Bitap (shift-or), bit by bit,
+/// in `O(n * (k + 1) * m / 64)` for `n` haystack bits, `m` pattern bits
+/// and `k` errors
+pub struct BitPattern {
+    bit_len: usize,
+    max_errors: usize,
+    // Copies of the pattern shifted right by `0..8` bits and their masks
+    shifted: [Vec<u8>; 8],
+    masks: [Vec<u8>; 8],
+    // Horspool skips of the windows of every shift by their last byte
+    skips: [Vec<usize>; 8],
+    // Bitap masks of the pattern bits that differ from a `0` and a `1` bit
+    mismatches: [Vec<u64>; 2],
+}
+
+impl BitPattern {
+    /// Pattern of the first `pattern_bits` bits of `pattern`
+    ///
+    /// **PANIC**: If `pattern_bits` is zero or large than pattern bit size
+    pub fn new(pattern: &[u8], pattern_bits: usize) -> Self {
+        assert!(pattern_bits != 0, "pattern_bits is zero");
+        assert!(
+            pattern_bits <= pattern.len() * 8,
+            "pattern_bits large than pattern bit size"
+        );
+
+        let pattern = pattern.to_vec();
+        let shifted: [Vec<u8>; 8] = std::array::from_fn(|shift| {
+            let mut copy = vec![0u8; (shift + pattern_bits).div_ceil(8)];
+            membitcpy(&mut copy, shift, pattern_bits, &pattern, 0);
+            copy
+        });
+        let masks: [Vec<u8>; 8] = std::array::from_fn(|shift| {
+            let mut mask = vec![0u8; (shift + pattern_bits).div_ceil(8)];
+            bit_fill(&mut mask, shift, pattern_bits);
+            mask
+        });
+        let skips = std::array::from_fn(|shift| horspool_skips(&shifted[shift], &masks[shift]));
+        let mismatches = std::array::from_fn(|bit| {
+            let mut mask = vec![0u64; pattern_bits.div_ceil(64)];
+            for index in 0..pattern_bits {
+                if get_bit(&pattern, index) != (bit == 1) {
+                    mask[index / 64] |= 1 << (index % 64);
+                }
+            }
+            mask
+        });
+
+        Self {
+            bit_len: pattern_bits,
+            max_errors: 0,
+            shifted,
+            masks,
+            skips,
+            mismatches,
+        }
+    }
+
+    /// Accepts matches with up to `max_errors` different bits, for noisy links
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Number of bits in the pattern
+    pub fn len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bit_len == 0
+    }
+
+    pub fn max_errors(&self) -> usize {
+        self.max_errors
+    }
+
+    /// Bit index of the first match at `start_bit` or after it,
+    /// `None` if there is no match
+    pub fn find(&self, haystack: &[u8], start_bit: usize) -> Option<usize> {
+        match self.max_errors {
+            0 => self.find_exact(haystack, start_bit),
+            _ => self.find_with_errors(haystack, start_bit),
+        }
+    }
+
+    /// Iterator over bit indexes of all matches at `start_bit` or after it.
+    /// Matches can overlap, the next search starts at the bit after a match
+    pub fn matches<'p, 'h>(&'p self, haystack: &'h [u8], start_bit: usize) -> Matches<'p, 'h> {
+        Matches {
+            pattern: self,
+            haystack,
+            next_bit: start_bit,
+        }
+    }
+
+    // Horspool search, the window of `shift` at byte `byte_index` is the
+    // match at bit `byte_index * 8 + shift`
+    fn find_exact(&self, haystack: &[u8], start_bit: usize) -> Option<usize> {
+        let mut windows: [usize; 8] = std::array::from_fn(|shift| (start_bit + 7 - shift) / 8);
+        loop {
+            // The window of the first bit that still fits into the haystack
+            let shift = (0..8)
+                .filter(|&shift| windows[shift] + self.shifted[shift].len() <= haystack.len())
+                .min_by_key(|&shift| windows[shift] * 8 + shift)?;
+            let bit_index = windows[shift] * 8 + shift;
+            if self.is_match_at(haystack, bit_index) {
+                return Some(bit_index);
+            }
+
+            let last_byte = haystack[windows[shift] + self.shifted[shift].len() - 1];
+            windows[shift] += self.skips[shift][last_byte as usize];
+        }
+    }
+
+    fn is_match_at(&self, haystack: &[u8], bit_index: usize) -> bool {
+        let start_byte_index = bit_index / 8;
+        let shift = bit_index % 8;
+
+        self.shifted[shift]
+            .iter()
+            .zip(&self.masks[shift])
+            .enumerate()
+            .all(|(index, (byte, mask))| (haystack[start_byte_index + index] ^ byte) & mask == 0)
+    }
+
+    // Bitap search, bit `i` of the state of a level is zero if the first
+    // `i + 1` bits of the pattern end at the current bit with up to
+    // `level` errors
+    fn find_with_errors(&self, haystack: &[u8], start_bit: usize) -> Option<usize> {
+        let words = self.bit_len.div_ceil(64);
+        // More errors than bits of the pattern change nothing
+        let levels = self.max_errors.min(self.bit_len) + 1;
+        let mut states = vec![u64::MAX; levels * words];
+        // State of the previous level before the current bit, shifted
+        let mut previous = vec![0u64; words];
+
+        let last_word = (self.bit_len - 1) / 64;
+        let last_mask = 1u64 << ((self.bit_len - 1) % 64);
+        for bit_index in start_bit..haystack.len() * 8 {
+            let mismatch = &self.mismatches[get_bit(haystack, bit_index) as usize];
+            for level in 0..levels {
+                let state = &mut states[level * words..(level + 1) * words];
+                let mut carry = 0;
+                for word in 0..words {
+                    let shifted = state[word] << 1 | carry;
+                    carry = state[word] >> 63;
+
+                    // Equal bit extends a match of the same level,
+                    // any bit extends a match of the previous level
+                    let mut next = shifted | mismatch[word];
+                    if level != 0 {
+                        next &= previous[word];
+                    }
+                    previous[word] = shifted;
+                    state[word] = next;
+                }
+            }
+
+            if states[(levels - 1) * words + last_word] & last_mask == 0 {
+                return Some(bit_index + 1 - self.bit_len);
+            }
+        }
+        None
+    }
+}
+
+// Skips of a Horspool window by its last byte: the distance to the
+// nearest earlier byte of the pattern that the last byte can match
+fn horspool_skips(pattern: &[u8], masks: &[u8]) -> Vec<usize> {
+    let last_index = pattern.len() - 1;
+    let mut skips = vec![pattern.len(); 256];
+    for (index, (byte, mask)) in pattern.iter().zip(masks).enumerate().take(last_index) {
+        for (haystack_byte, skip) in skips.iter_mut().enumerate() {
+            if (haystack_byte as u8 ^ byte) & mask == 0 {
+                *skip = last_index - index;
+            }
+        }
+    }
+    skips
+}
+
+#[cfg(test)]
+mod tests_bit_pattern {
+    use super::*;
+
+    #[test]
+    fn check_find_unaligned() {
+        // MPEG sync byte 0x47 at bit 3
+        let haystack = [0b00001000u8, 0b11100000u8, 0b00000000u8];
+        let pattern = BitPattern::new(&[0x47], 8);
+        assert_eq!(pattern.find(&haystack, 0), Some(3));
+        assert_eq!(pattern.find(&haystack, 4), None);
+    }
+
+    #[test]
+    fn check_max_errors() {
+        // HDLC flag 0x7E at bit 5 with one flipped bit
+        let haystack = [0b00000011u8, 0b11010000u8];
+        let exact = BitPattern::new(&[0x7E], 8);
+        assert_eq!(exact.find(&haystack, 0), None);
+
+        let noisy = BitPattern::new(&[0x7E], 8).with_max_errors(1);
+        assert_eq!(noisy.find(&haystack, 0), Some(5));
+    }
+
+    #[test]
+    fn check_matches_overlap() {
+        // Pattern `101` in `10101000 00010100`
+        let haystack = [0b10101000u8, 0b00010100u8];
+        let pattern = BitPattern::new(&[0b10100000], 3);
+        let matches: Vec<usize> = pattern.matches(&haystack, 0).collect();
+        assert_eq!(matches, [0, 2, 11]);
+
+        let matches: Vec<usize> = pattern.matches(&haystack, 1).collect();
+        assert_eq!(matches, [2, 11]);
+    }
+
+    #[test]
+    fn check_long_pattern() {
+        let mut haystack = vec![0u8; 100];
+        let pattern = [0xDEu8, 0xAD, 0xBE, 0xEF, 0x55];
+        membitcpy(&mut haystack, 517, 37, &pattern, 0);
+
+        let pattern = BitPattern::new(&pattern, 37);
+        assert_eq!(pattern.find(&haystack, 0), Some(517));
+        assert_eq!(pattern.find(&haystack, 518), None);
+    }
+
+    #[test]
+    fn check_against_naive() {
+        let mut state = 0x2545F491u32;
+        let haystack: Vec<u8> = (0..300)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                // Mostly ones, so short patterns of ones match often
+                (state | state >> 8) as u8
+            })
+            .collect();
+
+        // A long pattern from the haystack, and the same with one flipped bit
+        let mut long = vec![0u8; 9];
+        membitcpy(&mut long, 0, 70, &haystack, 1003);
+        let mut flipped = long.clone();
+        flipped[4] ^= 0b00100000;
+
+        for (pattern, pattern_bits) in [
+            (vec![0xFFu8], 5),
+            (vec![0xF7, 0xBF], 13),
+            (long, 70),
+            (flipped, 70),
+        ] {
+            for max_errors in [0, 1, 3] {
+                let bit_pattern =
+                    BitPattern::new(&pattern, pattern_bits).with_max_errors(max_errors);
+                let expected: Vec<usize> = (0..=haystack.len() * 8 - pattern_bits)
+                    .filter(|&bit_index| {
+                        let errors = (0..pattern_bits)
+                            .filter(|&index| {
+                                get_bit(&haystack, bit_index + index) != get_bit(&pattern, index)
+                            })
+                            .count();
+                        errors <= max_errors
+                    })
+                    .collect();
+
+                let matches: Vec<usize> = bit_pattern.matches(&haystack, 0).collect();
+                assert_eq!(
+                    matches, expected,
+                    "{pattern_bits} bits, {max_errors} errors"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn check_short_haystack() {
+        let pattern = BitPattern::new(&[0xFF, 0xFF], 12);
+        assert_eq!(pattern.find(&[0xFF], 0), None);
+        assert_eq!(pattern.matches(&[], 0).next(), None);
+    }
+}
+
+/// Iterator over bit indexes of matches of a [`BitPattern`],
+/// created by [`BitPattern::matches`]
+pub struct Matches<'p, 'h> {
+    pattern: &'p BitPattern,
+    haystack: &'h [u8],
+    next_bit: usize,
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bit_index = self.pattern.find(self.haystack, self.next_bit)?;
+        self.next_bit = bit_index + 1;
+        Some(bit_index)
+    }
+}
+
+/// Bit index of the first `pattern_bits` bits of `pattern` in `haystack`
+/// at `start_bit` or after it, `None` if there is no match. To search
+/// several times or to tolerate bit errors use [`BitPattern`]
+///
+/// **PANIC**: If `pattern_bits` is zero or large than pattern bit size
+pub fn find_bit_pattern(
+    haystack: &[u8],
+    pattern: &[u8],
+    pattern_bits: usize,
+    start_bit: usize,
+) -> Option<usize> {
+    BitPattern::new(pattern, pattern_bits).find(haystack, start_bit)
+}
+
+#[cfg(test)]
+mod tests_find_bit_pattern {
+    use super::*;
+
+    #[test]
+    fn check_find() {
+        let haystack = [0b01111110u8, 0b00111111u8, 0b00000000u8];
+        assert_eq!(find_bit_pattern(&haystack, &[0x7E], 8, 0), Some(0));
+        assert_eq!(find_bit_pattern(&haystack, &[0x7E], 8, 1), Some(9));
+        assert_eq!(find_bit_pattern(&haystack, &[0x7E], 8, 10), None);
+    }
+}
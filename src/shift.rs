@@ -0,0 +1,325 @@
+use std::ops::{Index, IndexMut};
+
+use crate::logic::bit_fill;
+use crate::{bit_clean, membitcpy, membitmove};
+
+/// Shifts `bit_size` bits of target at `bit_offset` by `shift` bits to the
+/// start (to the most significant bits), the vacated bits at the end are set
+/// to `fill`. To shift a whole buffer pass zero offset and its bit size
+///
+/// **PANIC**: If out of range target
+pub fn shift_left_bits<T>(
+    target: &mut T,
+    bit_offset: usize,
+    bit_size: usize,
+    shift: usize,
+    fill: bool,
+) where
+    T: IndexMut<usize, Output = u8>,
+{
+    let shift = shift.min(bit_size);
+    let kept = bit_size - shift;
+
+    move_bits(target, bit_offset, kept, bit_offset + shift);
+    set_bits(target, bit_offset + kept, shift, fill);
+}
+
+#[cfg(test)]
+mod tests_shift_left_bits {
+    use super::*;
+
+    #[test]
+    fn check_buffer() {
+        let mut target = [0b00010110u8, 0b11000001u8];
+        shift_left_bits(&mut target, 0, 16, 3, false);
+        assert_eq!(target, [0b10110110, 0b00001000]);
+
+        shift_left_bits(&mut target, 0, 16, 5, true);
+        assert_eq!(target, [0b11000001, 0b00011111]);
+    }
+
+    #[test]
+    fn check_range() {
+        let mut target = [0b11100110u8, 0b10111111u8];
+        shift_left_bits(&mut target, 3, 10, 4, false);
+        assert_eq!(target, [0b11101011, 0b10000111]);
+    }
+
+    #[test]
+    fn check_out_of_size() {
+        let mut target = [0b10101010u8; 3];
+        shift_left_bits(&mut target, 4, 16, 20, true);
+        assert_eq!(target, [0b10101111, 0b11111111, 0b11111010]);
+    }
+}
+
+/// Shifts `bit_size` bits of target at `bit_offset` by `shift` bits to the
+/// end (to the least significant bits), the vacated bits at the start are
+/// set to `fill`. To shift a whole buffer pass zero offset and its bit size
+///
+/// **PANIC**: If out of range target
+pub fn shift_right_bits<T>(
+    target: &mut T,
+    bit_offset: usize,
+    bit_size: usize,
+    shift: usize,
+    fill: bool,
+) where
+    T: IndexMut<usize, Output = u8>,
+{
+    let shift = shift.min(bit_size);
+    let kept = bit_size - shift;
+
+    move_bits(target, bit_offset + shift, kept, bit_offset);
+    set_bits(target, bit_offset, shift, fill);
+}
+
+#[cfg(test)]
+mod tests_shift_right_bits {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_buffer() {
+        let mut target = [0b00010110u8, 0b11000001u8];
+        shift_right_bits(&mut target, 0, 16, 3, true);
+        assert_eq!(target, [0b11100010, 0b11011000]);
+    }
+
+    #[test]
+    fn check_range() {
+        let mut target = [0b11100110u8, 0b10111111u8];
+        shift_right_bits(&mut target, 3, 10, 4, false);
+        assert_eq!(target, [0b11100000, 0b01101111]);
+    }
+
+    #[test]
+    fn check_against_membitmove() {
+        let origin = test_bytes(400, 0);
+
+        for (bit_offset, bit_size) in [(0, 3200), (8, 3000), (5, 3100), (13, 2011)] {
+            for shift in [0, 1, 7, 8, 63, 64, 100, 1000] {
+                let mut target = origin.clone();
+                shift_right_bits(&mut target, bit_offset, bit_size, shift, true);
+                let mut expected = origin.clone();
+                membitmove(
+                    &mut expected,
+                    bit_offset + shift,
+                    bit_size - shift,
+                    bit_offset,
+                );
+                bit_fill(&mut expected, bit_offset, shift);
+                assert_eq!(target, expected, "right {bit_offset} {bit_size} {shift}");
+
+                let mut target = origin.clone();
+                shift_left_bits(&mut target, bit_offset, bit_size, shift, false);
+                let mut expected = origin.clone();
+                membitmove(
+                    &mut expected,
+                    bit_offset,
+                    bit_size - shift,
+                    bit_offset + shift,
+                );
+                bit_clean(&mut expected, bit_offset + bit_size - shift, shift);
+                assert_eq!(target, expected, "left {bit_offset} {bit_size} {shift}");
+            }
+        }
+    }
+
+    #[test]
+    fn check_long() {
+        let mut target = [0b11111111u8; 20];
+        shift_right_bits(&mut target, 0, 160, 77, false);
+
+        let mut expected = [0b11111111u8; 20];
+        bit_clean(&mut expected, 0, 77);
+        assert_eq!(target, expected);
+    }
+}
+
+/// Rotates `bit_size` bits of target at `bit_offset` by `shift` bits to the
+/// start, the bits that are shifted out at the start come in at the end
+///
+/// **PANIC**: If out of range target
+pub fn rotate_left_bits<T>(target: &mut T, bit_offset: usize, bit_size: usize, shift: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    if bit_size == 0 {
+        return;
+    }
+    let shift = shift % bit_size;
+
+    // Save the shorter part aside, then move the longer one in place
+    if shift <= bit_size - shift {
+        let saved = save_bits(target, bit_offset, shift);
+        shift_left_bits(target, bit_offset, bit_size, shift, false);
+        membitcpy(target, bit_offset + bit_size - shift, shift, &saved, 0);
+    } else {
+        let rest = bit_size - shift;
+        let saved = save_bits(target, bit_offset + shift, rest);
+        shift_right_bits(target, bit_offset, bit_size, rest, false);
+        membitcpy(target, bit_offset, rest, &saved, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests_rotate_left_bits {
+    use super::*;
+
+    #[test]
+    fn check_buffer() {
+        let mut target = [0b10010110u8, 0b11000001u8];
+        rotate_left_bits(&mut target, 0, 16, 3);
+        assert_eq!(target, [0b10110110, 0b00001100]);
+
+        rotate_left_bits(&mut target, 0, 16, 13 + 16);
+        assert_eq!(target, [0b10010110, 0b11000001]);
+    }
+
+    #[test]
+    fn check_range() {
+        let mut target = [0b00011100u8, 0b00000000u8];
+        rotate_left_bits(&mut target, 3, 7, 2);
+        assert_eq!(target, [0b00010000, 0b11000000]);
+    }
+}
+
+/// Rotates `bit_size` bits of target at `bit_offset` by `shift` bits to the
+/// end, the bits that are shifted out at the end come in at the start
+///
+/// **PANIC**: If out of range target
+pub fn rotate_right_bits<T>(target: &mut T, bit_offset: usize, bit_size: usize, shift: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    if bit_size == 0 {
+        return;
+    }
+    rotate_left_bits(target, bit_offset, bit_size, bit_size - shift % bit_size);
+}
+
+#[cfg(test)]
+mod tests_rotate_right_bits {
+    use super::*;
+    use crate::test_bytes;
+
+    #[test]
+    fn check_round_trip() {
+        let origin = test_bytes(20, 0);
+
+        let mut target = origin.clone();
+        rotate_right_bits(&mut target, 5, 147, 61);
+        assert_ne!(target, origin);
+        rotate_left_bits(&mut target, 5, 147, 61);
+        assert_eq!(target, origin);
+
+        rotate_right_bits(&mut target, 5, 147, 147);
+        assert_eq!(target, origin);
+    }
+
+    #[test]
+    fn check_range() {
+        let mut target = [0b00010000u8, 0b11000000u8];
+        rotate_right_bits(&mut target, 3, 7, 2);
+        assert_eq!(target, [0b00011100, 0b00000000]);
+    }
+}
+
+// Same as `membitmove`, but the whole bytes of the target are moved by
+// `u64` words, only the bits before and after them are moved bit by bit
+fn move_bits<T>(target: &mut T, target_bit_offset: usize, bit_size: usize, source_bit_offset: usize)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    let head = ((8 - target_bit_offset % 8) % 8).min(bit_size);
+    let words_num = (bit_size - head) / 64;
+    let body = words_num * 64;
+    let tail = bit_size - head - body;
+
+    // As in `membitmove`, the parts are moved from the start when the
+    // target is ahead of the source, otherwise from the end
+    if target_bit_offset <= source_bit_offset {
+        membitmove(target, target_bit_offset, head, source_bit_offset);
+        for word_index in 0..words_num {
+            move_word(
+                target,
+                target_bit_offset + head,
+                source_bit_offset + head,
+                word_index,
+            );
+        }
+        membitmove(
+            target,
+            target_bit_offset + head + body,
+            tail,
+            source_bit_offset + head + body,
+        );
+    } else {
+        membitmove(
+            target,
+            target_bit_offset + head + body,
+            tail,
+            source_bit_offset + head + body,
+        );
+        for word_index in (0..words_num).rev() {
+            move_word(
+                target,
+                target_bit_offset + head,
+                source_bit_offset + head,
+                word_index,
+            );
+        }
+        membitmove(target, target_bit_offset, head, source_bit_offset);
+    }
+}
+
+// Moves the word at `word_index` of the bits at `source_bit_offset` to the
+// word at `word_index` of the whole bytes at `target_bit_offset`
+fn move_word<T>(
+    target: &mut T,
+    target_bit_offset: usize,
+    source_bit_offset: usize,
+    word_index: usize,
+) where
+    T: IndexMut<usize, Output = u8>,
+{
+    let source_byte_index = source_bit_offset / 8 + word_index * 8;
+    let shift = source_bit_offset % 8;
+
+    let word = (0..8).fold(0u64, |word, index| {
+        (word << 8) | target[source_byte_index + index] as u64
+    });
+    // The bits of the word that are in the next byte
+    let word = match shift {
+        0 => word,
+        _ => (word << shift) | (target[source_byte_index + 8] >> (8 - shift)) as u64,
+    };
+
+    let target_byte_index = target_bit_offset / 8 + word_index * 8;
+    for (index, byte) in word.to_be_bytes().into_iter().enumerate() {
+        target[target_byte_index + index] = byte;
+    }
+}
+
+// Sets `bit_size` bits of target at `bit_offset` to `fill`
+fn set_bits<T>(target: &mut T, bit_offset: usize, bit_size: usize, fill: bool)
+where
+    T: IndexMut<usize, Output = u8>,
+{
+    if fill {
+        bit_fill(target, bit_offset, bit_size);
+    } else {
+        bit_clean(target, bit_offset, bit_size);
+    }
+}
+
+// Copies `bit_size` bits of target at `bit_offset` to the start of a new buffer
+fn save_bits<T>(target: &T, bit_offset: usize, bit_size: usize) -> Vec<u8>
+where
+    T: Index<usize, Output = u8>,
+{
+    let mut saved = vec![0u8; bit_size.div_ceil(8)];
+    membitcpy(&mut saved, 0, bit_size, target, bit_offset);
+    saved
+}
@@ -0,0 +1,581 @@
+/// Reads `out.len()` unsigned integers of `width` bits, that are stored
+/// back to back in source starting at `bit_offset`, the same as a
+/// [`bit_read`](crate::bit_read) call for every value, but in bulk.
+/// SSE2, AVX2 or NEON is used when the CPU supports it to load the words
+/// of the values and shift them, the last values are unpacked by scalar
+/// code
+///
+/// **PANIC**: If `width` is zero or large than 32, or out of range source
+pub fn unpack_u32(source: &[u8], bit_offset: usize, width: usize, out: &mut [u32]) {
+    assert!(width != 0 && width <= 32, "width out of 1..=32");
+    assert!(
+        bit_offset + out.len() * width <= source.len() * 8,
+        "values out of range source"
+    );
+
+    let mut done = 0;
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is supported, and the values are in range source
+            done = unsafe { x86::unpack_avx2(source, bit_offset, width, out) };
+        } else if is_x86_feature_detected!("sse2") {
+            // SAFETY: SSE2 is supported, and the values are in range source
+            done = unsafe { x86::unpack_sse2(source, bit_offset, width, out) };
+        }
+    }
+
+    #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // SAFETY: NEON is supported, and the values are in range source
+            done = unsafe { neon::unpack(source, bit_offset, width, out) };
+        }
+    }
+
+    scalar::unpack(source, bit_offset + done * width, width, &mut out[done..]);
+}
+
+#[cfg(test)]
+mod tests_unpack_u32 {
+    use super::*;
+    use crate::bit_read;
+    use crate::test_bytes;
+
+    // Unpacks by `bit_read` for every value
+    fn expected<S>(source: &S, bit_offset: usize, width: usize, len: usize) -> Vec<u32>
+    where
+        S: std::ops::Index<usize, Output = u8>,
+    {
+        (0..len)
+            .map(|index| {
+                let mut target = [0u8; 4];
+                bit_read(source, bit_offset + index * width, width, &mut target, 4);
+                u32::from_be_bytes(target)
+            })
+            .collect()
+    }
+
+    fn source() -> Vec<u8> {
+        test_bytes(600, 0)
+    }
+
+    #[test]
+    fn check_against_bit_read() {
+        let source = source();
+        for width in 1..=32 {
+            for bit_offset in [0, 1, 5, 8, 13] {
+                let len = (source.len() * 8 - bit_offset) / width;
+                let mut out = vec![0u32; len];
+                unpack_u32(&source, bit_offset, width, &mut out);
+                assert_eq!(out, expected(&source, bit_offset, width, len));
+            }
+        }
+    }
+
+    #[test]
+    fn check_scalar_against_bit_read() {
+        let source = source();
+        for width in [1, 7, 11, 13, 31, 32] {
+            let len = (source.len() * 8 - 3) / width;
+            let mut out = vec![0u32; len];
+            scalar::unpack(&source, 3, width, &mut out);
+            assert_eq!(out, expected(&source, 3, width, len));
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn check_x86_against_bit_read() {
+        let source = source();
+        for width in [1, 3, 11, 17, 32] {
+            let len = (source.len() * 8 - 6) / width;
+
+            let mut out = vec![0u32; len];
+            // SAFETY: SSE2 is a part of x86_64, and the values are in range source
+            let done = unsafe { x86::unpack_sse2(&source, 6, width, &mut out) };
+            scalar::unpack(&source, 6 + done * width, width, &mut out[done..]);
+            assert_eq!(out, expected(&source, 6, width, len));
+
+            if is_x86_feature_detected!("avx2") {
+                let mut out = vec![0u32; len];
+                // SAFETY: AVX2 is supported, and the values are in range source
+                let done = unsafe { x86::unpack_avx2(&source, 6, width, &mut out) };
+                scalar::unpack(&source, 6 + done * width, width, &mut out[done..]);
+                assert_eq!(out, expected(&source, 6, width, len));
+            }
+        }
+    }
+
+    #[test]
+    fn check_tail() {
+        // The last values are closer than a word to the end of the source
+        let source = [0b10110011u8, 0b10001111u8, 0b01010101u8];
+        let mut out = [0u32; 4];
+        unpack_u32(&source, 2, 5, &mut out);
+        assert_eq!(out, [0b11001, 0b11000, 0b11110, 0b10101]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_out_of_range() {
+        let mut out = [0u32; 3];
+        unpack_u32(&[0u8; 2], 0, 6, &mut out);
+    }
+}
+
+/// Writes `values` back to back as `width` bit fields to target starting at
+/// `bit_offset`, the same as a [`bit_write`](crate::bit_write) call for every
+/// value, but in bulk
+///
+/// **NOTE**: The writing to the target is scalar, SSE2, AVX2 or NEON is only
+/// used when the CPU supports it to cut the values and join them to pairs
+///
+/// Unlike [`bit_write`](crate::bit_write), the bits of the fields are replaced,
+/// so the target does not have to be cleaned, and the bits around the fields
+/// are kept. Bits of the values that are out of `width` are discarded
+///
+/// **PANIC**: If `width` is zero or large than 32, or out of range target
+pub fn pack_u32(values: &[u32], target: &mut [u8], bit_offset: usize, width: usize) {
+    assert!(width != 0 && width <= 32, "width out of 1..=32");
+    assert!(
+        bit_offset + values.len() * width <= target.len() * 8,
+        "values out of range target"
+    );
+
+    let mut packer = scalar::Packer::new(target, bit_offset);
+    let to_pairs = pick_to_pairs();
+
+    // Neighbouring values are joined to pairs of `2 * width` bits,
+    // so the packer takes twice less pushes
+    let mut pairs = [0u64; 64];
+    let mut chunks = values.chunks_exact(pairs.len() * 2);
+    for chunk in &mut chunks {
+        // SAFETY: `to_pairs` is picked for the features the CPU supports,
+        // and the values and the pairs are only accessed in range of the slices
+        let done = unsafe { to_pairs(chunk, width, &mut pairs) };
+        scalar::to_pairs(&chunk[done * 2..], width, &mut pairs[done..]);
+        for pair in pairs {
+            packer.push(pair, width * 2);
+        }
+    }
+
+    let mask = u32::MAX >> (32 - width);
+    for value in chunks.remainder() {
+        packer.push((value & mask) as u64, width);
+    }
+    packer.finish();
+}
+
+#[cfg(test)]
+mod tests_pack_u32 {
+    use super::*;
+    use crate::test_bytes;
+    use crate::{bit_clean, bit_write};
+
+    // Packs by `bit_write` for every value
+    fn expected(values: &[u32], target: &[u8], bit_offset: usize, width: usize) -> Vec<u8> {
+        let mut expected = target.to_vec();
+        bit_clean(&mut expected, bit_offset, values.len() * width);
+        for (index, value) in values.iter().enumerate() {
+            let source = value.to_be_bytes();
+            bit_write(&mut expected, bit_offset + index * width, width, &source, 4);
+        }
+        expected
+    }
+
+    #[test]
+    fn check_against_bit_write() {
+        let values: Vec<u32> = (0..1000u32)
+            .map(|index| index.wrapping_mul(0x9E37_79B9))
+            .collect();
+        let origin = test_bytes(4200, 0);
+
+        for width in 1..=32 {
+            for bit_offset in [0, 3, 8, 15] {
+                let mut target = origin.clone();
+                pack_u32(&values, &mut target, bit_offset, width);
+                assert_eq!(target, expected(&values, &origin, bit_offset, width));
+            }
+        }
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let values: Vec<u32> = (0..777u32).map(|index| index * 13 % 2048).collect();
+        let mut target = vec![0u8; (777 * 11 + 5usize).div_ceil(8)];
+        pack_u32(&values, &mut target, 5, 11);
+
+        let mut out = vec![0u32; values.len()];
+        unpack_u32(&target, 5, 11, &mut out);
+        assert_eq!(out, values);
+    }
+
+    #[test]
+    fn check_keeps_neighbours() {
+        let mut target = [0b11111111u8; 2];
+        pack_u32(&[0, 0b111], &mut target, 4, 3);
+        assert_eq!(target, [0b11110001, 0b11111111]);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn check_x86_pairs() {
+        let values: Vec<u32> = (0..64u32)
+            .map(|index| index.wrapping_mul(0x9E37_79B9))
+            .collect();
+        for width in [1, 9, 32] {
+            let mut expected = [0u64; 32];
+            scalar::to_pairs(&values, width, &mut expected);
+
+            let mut pairs = [0u64; 32];
+            // SAFETY: SSE2 is a part of x86_64
+            let done = unsafe { x86::to_pairs_sse2(&values, width, &mut pairs) };
+            assert_eq!(done, 32);
+            assert_eq!(pairs, expected);
+
+            if is_x86_feature_detected!("avx2") {
+                let mut pairs = [0u64; 32];
+                // SAFETY: AVX2 is supported
+                let done = unsafe { x86::to_pairs_avx2(&values, width, &mut pairs) };
+                assert_eq!(done, 32);
+                assert_eq!(pairs, expected);
+            }
+        }
+    }
+}
+
+// Joins every two neighbouring values to `first << width | second`,
+// the values are cut to `width` bits. Returns the number of joined pairs,
+// the rest of the pairs is left for the scalar code
+type ToPairs = unsafe fn(&[u32], usize, &mut [u64]) -> usize;
+
+// Picks the way to join values to pairs, that the CPU supports
+fn pick_to_pairs() -> ToPairs {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return x86::to_pairs_avx2;
+        }
+        if is_x86_feature_detected!("sse2") {
+            return x86::to_pairs_sse2;
+        }
+    }
+
+    #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return neon::to_pairs;
+        }
+    }
+
+    no_pairs
+}
+
+// Leaves all pairs for the scalar code
+unsafe fn no_pairs(_values: &[u32], _width: usize, _pairs: &mut [u64]) -> usize {
+    0
+}
+
+mod scalar {
+    // Reads 8 bytes from `index` as a big-endian number,
+    // the bytes out of the source are zero
+    pub(super) fn load_be(source: &[u8], index: usize) -> u64 {
+        match source.get(index..index + 8) {
+            Some(bytes) => u64::from_be_bytes(bytes.try_into().unwrap()),
+            None => {
+                let mut bytes = [0u8; 8];
+                let tail = &source[index..];
+                bytes[..tail.len()].copy_from_slice(tail);
+                u64::from_be_bytes(bytes)
+            }
+        }
+    }
+
+    pub(super) fn unpack(source: &[u8], bit_offset: usize, width: usize, out: &mut [u32]) {
+        for (index, value) in out.iter_mut().enumerate() {
+            let bit_index = bit_offset + index * width;
+
+            // A value takes at most 32 + 7 bits of the word, so it is whole
+            let word = load_be(source, bit_index / 8);
+            *value = ((word << (bit_index % 8)) >> (64 - width)) as u32;
+        }
+    }
+
+    pub(super) fn to_pairs(values: &[u32], width: usize, pairs: &mut [u64]) {
+        let mask = u32::MAX >> (32 - width);
+        for (pair, values) in pairs.iter_mut().zip(values.chunks_exact(2)) {
+            *pair = ((values[0] & mask) as u64) << width | (values[1] & mask) as u64;
+        }
+    }
+
+    // Writes bits to bytes in order, keeping the bits of the first
+    // and the last byte that are out of the written bits
+    pub(super) struct Packer<'t> {
+        target: &'t mut [u8],
+        byte_index: usize,
+        // Pending bits, that do not fill a whole byte yet
+        bits: u128,
+        bits_num: usize,
+    }
+
+    impl<'t> Packer<'t> {
+        pub(super) fn new(target: &'t mut [u8], bit_offset: usize) -> Self {
+            let byte_index = bit_offset / 8;
+            let bits_num = bit_offset % 8;
+
+            // Start with the bits of the first byte ahead of the offset
+            let bits = match bits_num {
+                0 => 0,
+                _ => (target[byte_index] >> (8 - bits_num)) as u128,
+            };
+            Self {
+                target,
+                byte_index,
+                bits,
+                bits_num,
+            }
+        }
+
+        // Appends `width` (up to 64) least significant bits of `value`,
+        // the other bits must be zero
+        pub(super) fn push(&mut self, value: u64, width: usize) {
+            self.bits = (self.bits << width) | value as u128;
+            self.bits_num += width;
+            while self.bits_num >= 8 {
+                self.bits_num -= 8;
+                self.target[self.byte_index] = (self.bits >> self.bits_num) as u8;
+                self.byte_index += 1;
+            }
+            self.bits &= (1 << self.bits_num) - 1;
+        }
+
+        pub(super) fn finish(self) {
+            if self.bits_num == 0 {
+                return;
+            }
+            let kept = 0b11111111 >> self.bits_num;
+            let byte = &mut self.target[self.byte_index];
+            *byte = ((self.bits << (8 - self.bits_num)) as u8) | (*byte & kept);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    // Unpacks values by four while the words of them are in range source,
+    // returns the number of unpacked values
+    //
+    // SAFETY: The CPU must support AVX2, the values must be in range source
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn unpack_avx2(
+        source: &[u8],
+        bit_offset: usize,
+        width: usize,
+        out: &mut [u32],
+    ) -> usize {
+        // Reverses the bytes of every 64-bit lane
+        let swap = _mm256_setr_epi8(
+            7, 6, 5, 4, 3, 2, 1, 0, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 15, 14,
+            13, 12, 11, 10, 9, 8,
+        );
+        // Low halves of the lanes
+        let low = _mm256_setr_epi32(0, 2, 4, 6, 0, 0, 0, 0);
+        let right = _mm_cvtsi64_si128(64 - width as i64);
+        let step = _mm256_set1_epi64x(4 * width as i64);
+        let seven = _mm256_set1_epi64x(7);
+
+        let base = bit_offset as i64;
+        let width = width as i64;
+        let mut bit_indexes =
+            _mm256_setr_epi64x(base, base + width, base + 2 * width, base + 3 * width);
+
+        let mut done = 0;
+        while done + 4 <= out.len() {
+            let last_bit_index = bit_offset + (done + 3) * width as usize;
+            if last_bit_index / 8 + 8 > source.len() {
+                break;
+            }
+
+            let byte_indexes = _mm256_srli_epi64::<3>(bit_indexes);
+            let words = _mm256_i64gather_epi64::<1>(source.as_ptr() as *const i64, byte_indexes);
+            let words = _mm256_shuffle_epi8(words, swap);
+            let words = _mm256_sllv_epi64(words, _mm256_and_si256(bit_indexes, seven));
+            let values = _mm256_srl_epi64(words, right);
+
+            let values = _mm256_permutevar8x32_epi32(values, low);
+            _mm_storeu_si128(
+                out.as_mut_ptr().add(done) as *mut __m128i,
+                _mm256_castsi256_si128(values),
+            );
+
+            bit_indexes = _mm256_add_epi64(bit_indexes, step);
+            done += 4;
+        }
+        done
+    }
+
+    // Unpacks values by two while the words of them are in range source,
+    // returns the number of unpacked values. SSE2 has no shifts by lane,
+    // so every lane is shifted separately and the lanes are joined
+    //
+    // SAFETY: The CPU must support SSE2, the values must be in range source
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn unpack_sse2(
+        source: &[u8],
+        bit_offset: usize,
+        width: usize,
+        out: &mut [u32],
+    ) -> usize {
+        let right = _mm_cvtsi64_si128(64 - width as i64);
+
+        let mut done = 0;
+        while done + 2 <= out.len() {
+            let first = bit_offset + done * width;
+            let second = first + width;
+            if second / 8 + 8 > source.len() {
+                break;
+            }
+
+            // Little-endian words, the bytes of every lane are reversed
+            // by swapping the 16-bit parts and then the bytes in them
+            let words = _mm_unpacklo_epi64(
+                _mm_loadl_epi64(source.as_ptr().add(first / 8) as *const __m128i),
+                _mm_loadl_epi64(source.as_ptr().add(second / 8) as *const __m128i),
+            );
+            let words =
+                _mm_shufflehi_epi16::<0b00_01_10_11>(_mm_shufflelo_epi16::<0b00_01_10_11>(words));
+            let words = _mm_or_si128(_mm_slli_epi16::<8>(words), _mm_srli_epi16::<8>(words));
+
+            let first_words = _mm_sll_epi64(words, _mm_cvtsi64_si128((first % 8) as i64));
+            let second_words = _mm_sll_epi64(words, _mm_cvtsi64_si128((second % 8) as i64));
+            let words =
+                _mm_unpacklo_epi64(first_words, _mm_unpackhi_epi64(second_words, second_words));
+            let values = _mm_srl_epi64(words, right);
+
+            let values = _mm_shuffle_epi32::<0b00_00_10_00>(values);
+            _mm_storel_epi64(out.as_mut_ptr().add(done) as *mut __m128i, values);
+
+            done += 2;
+        }
+        done
+    }
+
+    // Joins values to pairs by eight, returns the number of pairs
+    //
+    // SAFETY: The CPU must support AVX2
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn to_pairs_avx2(values: &[u32], width: usize, pairs: &mut [u64]) -> usize {
+        let mask = _mm256_set1_epi32((u32::MAX >> (32 - width)) as i32);
+        let low = _mm256_set1_epi64x(u32::MAX as i64);
+        let left = _mm_cvtsi64_si128(width as i64);
+
+        let mut done = 0;
+        while done + 4 <= pairs.len() && (done + 4) * 2 <= values.len() {
+            // Lanes hold `first | second << 32` on little-endian
+            let lanes = _mm256_loadu_si256(values.as_ptr().add(done * 2) as *const __m256i);
+            let lanes = _mm256_and_si256(lanes, mask);
+            let first = _mm256_sll_epi64(_mm256_and_si256(lanes, low), left);
+            let second = _mm256_srli_epi64::<32>(lanes);
+            _mm256_storeu_si256(
+                pairs.as_mut_ptr().add(done) as *mut __m256i,
+                _mm256_or_si256(first, second),
+            );
+            done += 4;
+        }
+        done
+    }
+
+    // Joins values to pairs by four, returns the number of pairs
+    //
+    // SAFETY: The CPU must support SSE2
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn to_pairs_sse2(values: &[u32], width: usize, pairs: &mut [u64]) -> usize {
+        let mask = _mm_set1_epi32((u32::MAX >> (32 - width)) as i32);
+        let low = _mm_set1_epi64x(u32::MAX as i64);
+        let left = _mm_cvtsi64_si128(width as i64);
+
+        let mut done = 0;
+        while done + 2 <= pairs.len() && (done + 2) * 2 <= values.len() {
+            let lanes = _mm_loadu_si128(values.as_ptr().add(done * 2) as *const __m128i);
+            let lanes = _mm_and_si128(lanes, mask);
+            let first = _mm_sll_epi64(_mm_and_si128(lanes, low), left);
+            let second = _mm_srli_epi64::<32>(lanes);
+            _mm_storeu_si128(
+                pairs.as_mut_ptr().add(done) as *mut __m128i,
+                _mm_or_si128(first, second),
+            );
+            done += 2;
+        }
+        done
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+mod neon {
+    use std::arch::aarch64::*;
+
+    // Unpacks values by two while the words of them are in range source,
+    // returns the number of unpacked values. NEON shifts every lane by
+    // its own count, negative counts shift to the right
+    //
+    // SAFETY: The CPU must support NEON, the values must be in range source
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn unpack(
+        source: &[u8],
+        bit_offset: usize,
+        width: usize,
+        out: &mut [u32],
+    ) -> usize {
+        let right = vdupq_n_s64(-(64 - width as i64));
+
+        let mut done = 0;
+        while done + 2 <= out.len() {
+            let first = bit_offset + done * width;
+            let second = first + width;
+
+            if second / 8 + 8 > source.len() {
+                break;
+            }
+
+            // Reverses the bytes of the little-endian words
+            let words = vcombine_u8(
+                vld1_u8(source.as_ptr().add(first / 8)),
+                vld1_u8(source.as_ptr().add(second / 8)),
+            );
+            let words = vreinterpretq_u64_u8(vrev64q_u8(words));
+            let shifts = [(first % 8) as i64, (second % 8) as i64];
+            let words = vshlq_u64(words, vld1q_s64(shifts.as_ptr()));
+            let values = vshlq_u64(words, right);
+
+            vst1_u32(out.as_mut_ptr().add(done), vmovn_u64(values));
+            done += 2;
+        }
+        done
+    }
+
+    // Joins values to pairs by four, returns the number of pairs
+    //
+    // SAFETY: The CPU must support NEON
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn to_pairs(values: &[u32], width: usize, pairs: &mut [u64]) -> usize {
+        let mask = vdupq_n_u32(u32::MAX >> (32 - width));
+        let low = vdupq_n_u64(u32::MAX as u64);
+        let left = vdupq_n_s64(width as i64);
+
+        let mut done = 0;
+        while done + 2 <= pairs.len() && (done + 2) * 2 <= values.len() {
+            // Lanes hold `first | second << 32` on little-endian
+            let lanes = vandq_u32(vld1q_u32(values.as_ptr().add(done * 2)), mask);
+            let lanes = vreinterpretq_u64_u32(lanes);
+            let first = vshlq_u64(vandq_u64(lanes, low), left);
+            let second = vshrq_n_u64::<32>(lanes);
+            vst1q_u64(pairs.as_mut_ptr().add(done), vorrq_u64(first, second));
+            done += 2;
+        }
+        done
+    }
+}
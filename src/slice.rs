@@ -0,0 +1,472 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Bound, Index, IndexMut, Range, RangeBounds};
+
+use crate::cmp::{bit_hash, membiteq};
+use crate::{bit_clean, membitcpy};
+
+/// Borrowed view of `bit_len` bits of a storage, starting at `bit_offset`.
+/// Replaces passing `(buffer, bit_offset, bit_len)` triples around
+///
+/// **PANIC**: Access to bits out of the storage panics the same way
+/// as indexing of the storage
+pub struct BitSlice<'a, S: ?Sized> {
+    storage: &'a S,
+    bit_offset: usize,
+    bit_len: usize,
+}
+
+impl<S: ?Sized> Clone for BitSlice<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: ?Sized> Copy for BitSlice<'_, S> {}
+
+impl<'a, S> BitSlice<'a, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    pub fn new(storage: &'a S, bit_offset: usize, bit_len: usize) -> Self {
+        Self {
+            storage,
+            bit_offset,
+            bit_len,
+        }
+    }
+
+    /// The viewed storage
+    pub fn storage(&self) -> &'a S {
+        self.storage
+    }
+
+    /// Offset of the first bit of the view in the storage
+    pub fn bit_offset(&self) -> usize {
+        self.bit_offset
+    }
+
+    /// Number of bits in the view
+    pub fn len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bit_len == 0
+    }
+
+    /// Bit at `index` of the view, `None` if out of the view
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.bit_len {
+            return None;
+        }
+        Some(get_bit(self.storage, self.bit_offset + index))
+    }
+
+    /// Sub-view of the bits in `range` of the view
+    ///
+    /// **PANIC**: If `range` out of the view
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let range = to_range(range, self.bit_len);
+        Self::new(self.storage, self.bit_offset + range.start, range.len())
+    }
+
+    /// Divides the view into `[0, mid)` and `[mid, len)`
+    ///
+    /// **PANIC**: If `mid` large than length of the view
+    pub fn split_at(&self, mid: usize) -> (Self, Self) {
+        (self.slice(..mid), self.slice(mid..))
+    }
+
+    pub fn iter(&self) -> Iter<'a, S> {
+        Iter {
+            slice: *self,
+            front: 0,
+            back: self.bit_len,
+        }
+    }
+}
+
+impl<S> Index<usize> for BitSlice<'_, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    type Output = bool;
+
+    fn index(&self, index: usize) -> &bool {
+        match self.get(index) {
+            Some(true) => &true,
+            Some(false) => &false,
+            None => panic!("bit index out of the slice"),
+        }
+    }
+}
+
+impl<'a, S> IntoIterator for BitSlice<'a, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    type Item = bool;
+    type IntoIter = Iter<'a, S>;
+
+    fn into_iter(self) -> Iter<'a, S> {
+        self.iter()
+    }
+}
+
+impl<S> fmt::Debug for BitSlice<'_, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_bits(f, self.iter())
+    }
+}
+
+impl<S, O> PartialEq<BitSlice<'_, O>> for BitSlice<'_, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+    O: Index<usize, Output = u8> + ?Sized,
+{
+    fn eq(&self, other: &BitSlice<'_, O>) -> bool {
+        self.bit_len == other.bit_len
+            && membiteq(
+                self.storage,
+                self.bit_offset,
+                other.storage,
+                other.bit_offset,
+                self.bit_len,
+            )
+    }
+}
+
+impl<S> Eq for BitSlice<'_, S> where S: Index<usize, Output = u8> + ?Sized {}
+
+impl<S> Hash for BitSlice<'_, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        bit_hash(self.storage, self.bit_offset, self.bit_len, state);
+    }
+}
+
+#[cfg(test)]
+mod tests_bit_slice {
+    use super::*;
+
+    #[test]
+    fn check_get() {
+        let storage = [0b00000101u8, 0b10000000u8];
+        let slice = BitSlice::new(&storage, 5, 4);
+
+        assert_eq!(slice.len(), 4);
+        assert_eq!(slice.get(0), Some(true));
+        assert_eq!(slice.get(1), Some(false));
+        assert!(slice[2]);
+        assert!(slice[3]);
+        assert_eq!(slice.get(4), None);
+    }
+
+    #[test]
+    fn check_slice_and_split() {
+        let storage = [0b11110000u8, 0b00001111u8];
+        let slice = BitSlice::new(&storage, 2, 12);
+
+        let sub = slice.slice(1..5);
+        assert_eq!(sub.bit_offset(), 3);
+        assert_eq!(sub.iter().collect::<Vec<_>>(), [true, false, false, false]);
+
+        let (left, right) = slice.split_at(8);
+        assert_eq!(left.len(), 8);
+        assert_eq!(right.len(), 4);
+        assert_eq!(right.bit_offset(), 10);
+        assert_eq!(format!("{:?}", right), "[0011]");
+    }
+
+    #[test]
+    fn check_iter_rev() {
+        let storage = vec![0b10110000u8];
+        let slice = BitSlice::new(&storage, 0, 4);
+        let bits: Vec<bool> = slice.iter().rev().collect();
+        assert_eq!(bits, [true, true, false, true]);
+    }
+
+    #[test]
+    fn check_eq() {
+        let a = [0b00000101u8, 0b11001110u8, 0b10000000u8];
+        let b = vec![0b10111001u8, 0b11010000u8];
+
+        assert_eq!(BitSlice::new(&a, 5, 12), BitSlice::new(&b, 0, 12));
+        assert_ne!(BitSlice::new(&a, 5, 12), BitSlice::new(&b, 0, 11));
+        assert_ne!(BitSlice::new(&a, 5, 12), BitSlice::new(&b, 1, 12));
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_slice_out_of_range() {
+        let storage = [0u8; 1];
+        BitSlice::new(&storage, 0, 8).slice(4..9);
+    }
+}
+
+/// Mutable counterpart of [`BitSlice`]
+pub struct BitSliceMut<'a, T: ?Sized> {
+    storage: &'a mut T,
+    bit_offset: usize,
+    bit_len: usize,
+}
+
+impl<'a, T> BitSliceMut<'a, T>
+where
+    T: IndexMut<usize, Output = u8> + ?Sized,
+{
+    pub fn new(storage: &'a mut T, bit_offset: usize, bit_len: usize) -> Self {
+        Self {
+            storage,
+            bit_offset,
+            bit_len,
+        }
+    }
+
+    /// Offset of the first bit of the view in the storage
+    pub fn bit_offset(&self) -> usize {
+        self.bit_offset
+    }
+
+    /// Number of bits in the view
+    pub fn len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bit_len == 0
+    }
+
+    /// Bit at `index` of the view, `None` if out of the view
+    pub fn get(&self, index: usize) -> Option<bool> {
+        self.as_bit_slice().get(index)
+    }
+
+    /// Sets bit at `index` of the view
+    ///
+    /// **PANIC**: If `index` out of the view
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.bit_len, "bit index out of the slice");
+
+        let bit_index = self.bit_offset + index;
+        let mask = 0b10000000 >> (bit_index % 8);
+        if value {
+            self.storage[bit_index / 8] |= mask;
+        } else {
+            self.storage[bit_index / 8] &= !mask;
+        }
+    }
+
+    /// Shared view of the same bits
+    pub fn as_bit_slice(&self) -> BitSlice<'_, T> {
+        BitSlice::new(self.storage, self.bit_offset, self.bit_len)
+    }
+
+    /// Mutable sub-view of the bits in `range` of the view
+    ///
+    /// **PANIC**: If `range` out of the view
+    pub fn slice_mut(&mut self, range: impl RangeBounds<usize>) -> BitSliceMut<'_, T> {
+        let range = to_range(range, self.bit_len);
+        BitSliceMut::new(self.storage, self.bit_offset + range.start, range.len())
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.as_bit_slice().iter()
+    }
+
+    /// Resets all bits of the view to zero
+    pub fn clean(&mut self) {
+        bit_clean(self.storage, self.bit_offset, self.bit_len);
+    }
+
+    /// Overwrites the view with bits of `source`
+    ///
+    /// **PANIC**: If lengths of the views differ
+    pub fn copy_from_bit_slice<S>(&mut self, source: &BitSlice<'_, S>)
+    where
+        S: Index<usize, Output = u8> + ?Sized,
+    {
+        assert_eq!(self.bit_len, source.len(), "lengths of slices differ");
+
+        self.clean();
+        membitcpy(
+            self.storage,
+            self.bit_offset,
+            self.bit_len,
+            source.storage(),
+            source.bit_offset(),
+        );
+    }
+}
+
+impl<T> BitSliceMut<'_, T>
+where
+    T: IndexMut<usize, Output = u8> + AsMut<[u8]> + ?Sized,
+{
+    /// Divides the view into `[0, mid)` and `[mid, len)` over disjoint
+    /// bytes of the storage
+    ///
+    /// **PANIC**: If `mid` large than length of the view, or the bit
+    /// at `mid` is not the first bit of a byte of the storage
+    ///
+    /// **NOTE**: Halves divided inside a byte would share it, see
+    /// [`BitRangeMut`](crate::split::BitRangeMut) for division at any bit
+    pub fn split_at_mut(&mut self, mid: usize) -> (BitSliceMut<'_, [u8]>, BitSliceMut<'_, [u8]>) {
+        assert!(mid <= self.bit_len, "mid out of the slice");
+        let split_bit_index = self.bit_offset + mid;
+        assert!(
+            split_bit_index.is_multiple_of(8),
+            "mid is not on a byte boundary"
+        );
+
+        let (left, right) = self.storage.as_mut().split_at_mut(split_bit_index / 8);
+        (
+            BitSliceMut::new(left, self.bit_offset, mid),
+            BitSliceMut::new(right, 0, self.bit_len - mid),
+        )
+    }
+}
+
+impl<T> fmt::Debug for BitSliceMut<'_, T>
+where
+    T: IndexMut<usize, Output = u8> + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_bits(f, self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests_bit_slice_mut {
+    use super::*;
+
+    #[test]
+    fn check_set() {
+        let mut storage = [0b11111111u8, 0b00000000u8];
+        let mut slice = BitSliceMut::new(&mut storage, 6, 4);
+        slice.set(0, false);
+        slice.set(3, true);
+        assert_eq!(slice.get(3), Some(true));
+        assert_eq!(storage, [0b11111101, 0b01000000]);
+    }
+
+    #[test]
+    fn check_copy_from() {
+        let source = [0b00101100u8];
+        let mut storage = [0b11111111u8, 0b11111111u8];
+
+        let mut slice = BitSliceMut::new(&mut storage, 3, 10);
+        slice
+            .slice_mut(2..6)
+            .copy_from_bit_slice(&BitSlice::new(&source, 2, 4));
+        assert_eq!(storage, [0b11111101, 0b11111111]);
+    }
+
+    #[test]
+    fn check_split_at_mut() {
+        let mut storage = [0u8; 3];
+        let mut slice = BitSliceMut::new(&mut storage, 5, 15);
+        let (mut left, mut right) = slice.split_at_mut(11);
+        assert_eq!((left.bit_offset(), left.len()), (5, 11));
+        assert_eq!((right.bit_offset(), right.len()), (0, 4));
+
+        left.set(0, true);
+        left.set(10, true);
+        right.set(3, true);
+        assert_eq!(storage, [0b00000100, 0b00000001, 0b00010000]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_split_at_mut_unaligned() {
+        let mut storage = [0u8; 2];
+        BitSliceMut::new(&mut storage, 2, 12).split_at_mut(4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_copy_different_len() {
+        let source = [0u8; 1];
+        let mut storage = [0u8; 1];
+        BitSliceMut::new(&mut storage, 0, 3).copy_from_bit_slice(&BitSlice::new(&source, 0, 4));
+    }
+}
+
+/// Iterator over bits of a [`BitSlice`]
+pub struct Iter<'a, S: ?Sized> {
+    slice: BitSlice<'a, S>,
+    front: usize,
+    back: usize,
+}
+
+impl<S> Iterator for Iter<'_, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.slice.get(self.front - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.back - self.front, Some(self.back - self.front))
+    }
+}
+
+impl<S> DoubleEndedIterator for Iter<'_, S>
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    fn next_back(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.slice.get(self.back)
+    }
+}
+
+impl<S> ExactSizeIterator for Iter<'_, S> where S: Index<usize, Output = u8> + ?Sized {}
+
+// Bit at `bit_index` of the storage
+pub(crate) fn get_bit<S>(storage: &S, bit_index: usize) -> bool
+where
+    S: Index<usize, Output = u8> + ?Sized,
+{
+    storage[bit_index / 8] & (0b10000000 >> (bit_index % 8)) != 0
+}
+
+// Resolves `range` against a length, panics if it is out of the length
+pub(crate) fn to_range(range: impl RangeBounds<usize>, bit_len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(start) => start + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => end + 1,
+        Bound::Excluded(end) => *end,
+        Bound::Unbounded => bit_len,
+    };
+    assert!(start <= end && end <= bit_len, "bit range out of the slice");
+    start..end
+}
+
+fn debug_bits(f: &mut fmt::Formatter<'_>, bits: impl Iterator<Item = bool>) -> fmt::Result {
+    write!(f, "[")?;
+    for bit in bits {
+        write!(f, "{}", bit as u8)?;
+    }
+    write!(f, "]")
+}
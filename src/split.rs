@@ -0,0 +1,282 @@
+use std::ops::Index;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::atomic::atomic_bit_write;
+use crate::bit_read;
+
+/// Mutable view of a bit range of a byte buffer, that can be split at
+/// any bit into disjoint halves. The halves may share a boundary byte,
+/// but can still be written from different threads: bytes that are only
+/// partially covered by a view are updated with
+/// [`atomic_bit_write`], so bits of the neighbour are never clobbered
+///
+/// **NOTE**: Writes use relaxed ordering, results are visible to other
+/// threads after they are joined (for example by `std::thread::scope`)
+pub struct BitRangeMut<'a> {
+    bytes: &'a [AtomicU8],
+    bit_offset: usize,
+    bit_len: usize,
+}
+
+impl<'a> BitRangeMut<'a> {
+    /// View of all bits of `bytes`
+    pub fn new(bytes: &'a mut [u8]) -> Self {
+        let bit_len = bytes.len() * 8;
+
+        // SAFETY: `AtomicU8` has the same size and alignment as `u8`,
+        // and the exclusive borrow guarantees that there is no
+        // non-atomic access to the bytes while the view is alive
+        let bytes = unsafe { &*(bytes as *mut [u8] as *const [AtomicU8]) };
+        Self {
+            bytes,
+            bit_offset: 0,
+            bit_len,
+        }
+    }
+
+    /// Offset of the first bit of the view in the buffer
+    pub fn bit_offset(&self) -> usize {
+        self.bit_offset
+    }
+
+    /// Number of bits in the view
+    pub fn len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bit_len == 0
+    }
+
+    /// Divides the view into `[0, mid)` and `[mid, len)`
+    ///
+    /// **PANIC**: If `mid` large than length of the view
+    pub fn split_at_bit_mut(self, mid: usize) -> (Self, Self) {
+        assert!(mid <= self.bit_len, "mid out of the range");
+
+        let left = Self {
+            bytes: self.bytes,
+            bit_offset: self.bit_offset,
+            bit_len: mid,
+        };
+        let right = Self {
+            bytes: self.bytes,
+            bit_offset: self.bit_offset + mid,
+            bit_len: self.bit_len - mid,
+        };
+        (left, right)
+    }
+
+    /// Bit at `index` of the view, `None` if out of the view
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.bit_len {
+            return None;
+        }
+        let bit_index = self.bit_offset + index;
+        let byte = self.bytes[bit_index / 8].load(Ordering::Relaxed);
+        Some(byte & (0b10000000 >> (bit_index % 8)) != 0)
+    }
+
+    /// Overwrites `width` bits at `index` of the view with the least
+    /// significant bits of `value`
+    ///
+    /// **PANIC**: If the written bits are out of the view, or `width`
+    /// large than 64
+    pub fn write_bits(&mut self, index: usize, width: usize, value: u64) {
+        self.check_range(index, width);
+
+        let source = value.to_be_bytes();
+        atomic_bit_write(
+            self.bytes,
+            self.bit_offset + index,
+            width,
+            &source,
+            source.len(),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Overwrites `recordable_bit_size` bits at `index` of the view with
+    /// bits of `source` at `source_bit_offset`, the same as
+    /// [`membitcpy`](crate::membitcpy)
+    ///
+    /// **PANIC**: If the written bits are out of the view, or
+    /// out of range source
+    pub fn membitcpy<S>(
+        &mut self,
+        index: usize,
+        recordable_bit_size: usize,
+        source: &S,
+        source_bit_offset: usize,
+    ) where
+        S: Index<usize, Output = u8>,
+    {
+        self.check_range(index, recordable_bit_size);
+
+        // Copy by chunks no longer than a `u64`
+        let mut copied = 0;
+        while copied < recordable_bit_size {
+            let width = (recordable_bit_size - copied).min(64);
+
+            let mut chunk = [0u8; 8];
+            let chunk_len = chunk.len();
+            bit_read(
+                source,
+                source_bit_offset + copied,
+                width,
+                &mut chunk,
+                chunk_len,
+            );
+            atomic_bit_write(
+                self.bytes,
+                self.bit_offset + index + copied,
+                width,
+                &chunk,
+                chunk_len,
+                Ordering::Relaxed,
+            );
+
+            copied += width;
+        }
+    }
+
+    fn check_range(&self, index: usize, bit_size: usize) {
+        assert!(
+            index + bit_size <= self.bit_len,
+            "bit range out of the view"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_bit_range_mut {
+    use super::*;
+    use crate::membitcpy;
+
+    #[test]
+    fn check_split() {
+        let mut bytes = [0u8; 3];
+        let range = BitRangeMut::new(&mut bytes);
+        let (left, right) = range.split_at_bit_mut(5);
+        let (middle, right) = right.split_at_bit_mut(13);
+
+        assert_eq!((left.bit_offset(), left.len()), (0, 5));
+        assert_eq!((middle.bit_offset(), middle.len()), (5, 13));
+        assert_eq!((right.bit_offset(), right.len()), (18, 6));
+    }
+
+    #[test]
+    fn check_write_bits() {
+        let mut bytes = [0b11111111u8, 0b00000000u8, 0b11111111u8];
+        let mut range = BitRangeMut::new(&mut bytes);
+        range.write_bits(4, 16, 0b1010_0000_0000_0101);
+        assert_eq!(range.get(4), Some(true));
+        assert_eq!(range.get(5), Some(false));
+        assert_eq!(bytes, [0b11111010, 0b00000000, 0b01011111]);
+    }
+
+    #[test]
+    fn check_membitcpy_long() {
+        let source: Vec<u8> = (0..20).map(|index| index * 13).collect();
+        let mut bytes = [0u8; 20];
+        let mut range = BitRangeMut::new(&mut bytes);
+        range.membitcpy(3, 150, &source, 5);
+
+        let mut expected = [0u8; 20];
+        membitcpy(&mut expected, 3, 150, &source, 5);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn check_parallel_boundary() {
+        let mut bytes = [0u8; 64];
+        let range = BitRangeMut::new(&mut bytes);
+
+        // 7 bits for every writer, so most bytes are shared by two writers
+        let mut ranges = vec![];
+        let mut rest = range;
+        while rest.len() >= 7 {
+            let (head, tail) = rest.split_at_bit_mut(7);
+            ranges.push(head);
+            rest = tail;
+        }
+
+        std::thread::scope(|scope| {
+            for mut range in ranges {
+                scope.spawn(move || {
+                    for _ in 0..100 {
+                        range.write_bits(0, 7, 0);
+                        range.write_bits(0, 7, 0b1111111);
+                    }
+                });
+            }
+        });
+
+        // 64 * 8 = 512 bits, 73 writers cover 511 of them
+        let mut expected = [0b11111111u8; 64];
+        expected[63] = 0b11111110;
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_out_of_range() {
+        let mut bytes = [0u8; 2];
+        let (mut left, _) = BitRangeMut::new(&mut bytes).split_at_bit_mut(6);
+        left.write_bits(2, 5, 0);
+    }
+}
+
+/// Packs `values` back to back as `width` bit fields to `target`,
+/// starting at `bit_offset`. Disjoint ranges are written by rayon
+/// worker threads, neighbouring fields may share a byte
+///
+/// **PANIC**: If `width` large than 64 or the fields are out of target
+///
+/// **NOTE**: Bits of the values that are out of `width` are discarded
+#[cfg(feature = "rayon")]
+pub fn pack_parallel(values: &[u64], width: usize, target: &mut [u8], bit_offset: usize) {
+    use rayon::prelude::*;
+
+    // Number of values packed by one task
+    const CHUNK: usize = 4096;
+
+    let range = BitRangeMut::new(target);
+    let (_, mut rest) = range.split_at_bit_mut(bit_offset);
+
+    let mut ranges = Vec::with_capacity(values.len().div_ceil(CHUNK));
+    for chunk in values.chunks(CHUNK) {
+        let (head, tail) = rest.split_at_bit_mut(chunk.len() * width);
+        ranges.push(head);
+        rest = tail;
+    }
+
+    values
+        .par_chunks(CHUNK)
+        .zip(ranges)
+        .for_each(|(chunk, mut range)| {
+            for (index, value) in chunk.iter().enumerate() {
+                range.write_bits(index * width, width, *value);
+            }
+        });
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests_pack_parallel {
+    use super::*;
+    use crate::bit_write;
+
+    #[test]
+    fn check_against_sequential() {
+        let values: Vec<u64> = (0..10_000u64).map(|index| index * 7919 % 2048).collect();
+        let mut target = vec![0u8; (values.len() * 11 + 3).div_ceil(8)];
+        pack_parallel(&values, 11, &mut target, 3);
+
+        let mut expected = vec![0u8; target.len()];
+        for (index, value) in values.iter().enumerate() {
+            let source = value.to_be_bytes();
+            bit_write(&mut expected, 3 + index * 11, 11, &source, source.len());
+        }
+        assert_eq!(target, expected);
+    }
+}
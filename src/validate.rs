@@ -0,0 +1,178 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::int::max_hold;
+use crate::is_in_range;
+
+/// Error of a field whose value does not fit into its bit width
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRangeError {
+    /// Name of the field
+    pub field: &'static str,
+    /// Offset of the field in the message
+    pub bit_offset: usize,
+    /// Number of bits reserved for the field
+    pub bit_width: usize,
+    /// Value that was about to be encoded
+    pub value: u64,
+    /// Maximum value that `bit_width` bits can store
+    pub max: u64,
+}
+
+impl fmt::Display for FieldRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error: field '{}' (offset: {}, bit_size: {}) has value {}, which exceeds the maximum value {}.",
+            self.field, self.bit_offset, self.bit_width, self.value, self.max
+        )
+    }
+}
+
+impl Error for FieldRangeError {}
+
+/// All violations found by [`FieldValidator`] for a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRangeErrors(pub Vec<FieldRangeError>);
+
+impl fmt::Display for FieldRangeErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for FieldRangeErrors {}
+
+/// Accumulating validator, that collects all out of range fields
+/// of a message before encoding, instead of stopping at the first one
+///
+/// ```
+/// use bit_bi_parse::validate::FieldValidator;
+///
+/// let mut validator = FieldValidator::new();
+/// validator
+///     .check("field1", 0, 6, 63)
+///     .check("field2", 6, 4, 16)
+///     .check("field3", 10, 2, 4);
+///
+/// let errors = validator.finish().unwrap_err();
+/// assert_eq!(errors.0.len(), 2);
+/// assert_eq!(errors.0[0].field, "field2");
+/// ```
+#[derive(Debug, Default)]
+pub struct FieldValidator {
+    errors: Vec<FieldRangeError>,
+}
+
+impl FieldValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that `value` of `field` fits into `bit_width` bits,
+    /// otherwise records the violation
+    pub fn check(
+        &mut self,
+        field: &'static str,
+        bit_offset: usize,
+        bit_width: usize,
+        value: u64,
+    ) -> &mut Self {
+        let source = value.to_be_bytes();
+
+        // NOTE: All values of the source fit into a wider field
+        if !is_in_range(bit_width.min(64), &source, source.len()) {
+            self.errors.push(FieldRangeError {
+                field,
+                bit_offset,
+                bit_width,
+                value,
+                max: max_hold(bit_width as u32),
+            });
+        }
+        self
+    }
+
+    /// Violations collected so far
+    pub fn errors(&self) -> &[FieldRangeError] {
+        &self.errors
+    }
+
+    /// Finishes validation, returning all collected violations if any
+    pub fn finish(self) -> Result<(), FieldRangeErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(FieldRangeErrors(self.errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_field_validator {
+    use super::*;
+
+    #[test]
+    fn check_valid() {
+        let mut validator = FieldValidator::new();
+        validator
+            .check("field1", 0, 6, 63)
+            .check("field2", 6, 32, u32::MAX as u64)
+            .check("field3", 38, 64, u64::MAX)
+            .check("field4", 102, 0, 0);
+
+        assert!(validator.errors().is_empty());
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn check_collect_all() {
+        let mut validator = FieldValidator::new();
+        validator
+            .check("field1", 0, 6, 64)
+            .check("field2", 6, 4, 15)
+            .check("field3", 10, 1, 2);
+
+        let errors = validator.finish().unwrap_err();
+        assert_eq!(
+            errors.0,
+            vec![
+                FieldRangeError {
+                    field: "field1",
+                    bit_offset: 0,
+                    bit_width: 6,
+                    value: 64,
+                    max: 63,
+                },
+                FieldRangeError {
+                    field: "field3",
+                    bit_offset: 10,
+                    bit_width: 1,
+                    value: 2,
+                    max: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn check_display() {
+        let error = FieldRangeError {
+            field: "field1",
+            bit_offset: 0,
+            bit_width: 6,
+            value: 64,
+            max: 63,
+        };
+        assert_eq!(
+            error.to_string(),
+            "Error: field 'field1' (offset: 0, bit_size: 6) has value 64, which exceeds the maximum value 63."
+        );
+    }
+}